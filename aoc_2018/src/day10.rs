@@ -1,55 +1,30 @@
 // Day 10: The Stars Align
 //
-// This day requires a visualisation of the problem to solve. As such, this day is
-// compiled as a seperate binary. Run with: `cargo run day10`
-//
+use crate::ocr::{self, OcrError};
 use regex::Regex;
-use std::fs::File;
-use std::io::Read;
-use std::path::Path;
 use std::str;
 
-fn main() {
-    let path = Path::new("input/2018/day10.txt");
-    let display = path.display();
-    let mut file = match File::open(&path) {
-        Err(e) => panic!("Couldn't open {}: {}", display, e),
-        Ok(file) => file,
-    };
-
-    let mut contents = String::new();
-
-    file.read_to_string(&mut contents).unwrap();
-    let mut sky = NightSky::new(&contents);
-
-    for _ in 0..100_000 {
-        sky.tick();
-        let (w, h) = sky.dimensions();
-        if w <= 100 && h <= 100 {
-            println!("t @ {}s", sky.time);
-            println!("{}", sky.display().trim());
-        }
-    }
-}
-
 #[derive(Default, PartialEq, Debug, Eq, Clone)]
-struct Point {
+pub struct Point {
     x: isize,
     y: isize,
 }
 
 #[derive(Default, PartialEq, Debug, Eq, Clone)]
-struct Light {
+pub struct Light {
     position: Point,
     velocity: (isize, isize), //x velocity, y velocity
 }
 
 #[derive(Default, PartialEq, Debug, Eq, Clone)]
-struct NightSky {
+pub struct NightSky {
     lights: Vec<Light>,
     time: usize,
 }
 
+// The size in pixels that a light is drawn in SVG images
+const SVG_CELL: isize = 8;
+
 #[derive(Clone, Copy, Debug, Default)]
 struct Bounds {
     minx: isize,
@@ -59,6 +34,37 @@ struct Bounds {
 }
 
 impl Bounds {
+    fn union(&self, other: &Bounds) -> Bounds {
+        Bounds {
+            minx: self.minx.min(other.minx),
+            maxx: self.maxx.max(other.maxx),
+            miny: self.miny.min(other.miny),
+            maxy: self.maxy.max(other.maxy),
+        }
+    }
+
+    // The opening of an SVG image covering these bounds, with a cell of margin all round
+    //
+    fn svg_open(&self) -> String {
+        let (x, y) = (self.minx - 1, self.miny - 1);
+        let (w, h) = (self.maxx - self.minx + 3, self.maxy - self.miny + 3);
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\" width=\"{}\" height=\"{}\">\n\
+             \x20 <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"black\"/>\n\
+             \x20 <g fill=\"gold\">\n",
+            x,
+            y,
+            w,
+            h,
+            w * SVG_CELL,
+            h * SVG_CELL,
+            x,
+            y,
+            w,
+            h
+        )
+    }
+
     fn normalaise(&self, p: &Point) -> Point {
         let (x, y): (isize, isize);
         if self.minx >= 0 {
@@ -76,7 +82,7 @@ impl Bounds {
 }
 
 impl NightSky {
-    fn new(input: &str) -> NightSky {
+    pub fn new(input: &str) -> NightSky {
         let re = Regex::new(
             r"(?x)
                     position=<\s*(?P<x>[-0-9]+),\s*(?P<y>[-0-9]+)>
@@ -103,15 +109,89 @@ impl NightSky {
         NightSky { lights, time: 0 }
     }
 
-    fn tick(&mut self) {
+    pub fn time(&self) -> usize {
+        self.time
+    }
+
+    pub fn tick(&mut self) {
+        self.step(1);
+    }
+
+    // Move every light `seconds` forward (or backward) in time
+    //
+    fn step(&mut self, seconds: isize) {
         self.lights.iter_mut().for_each(|l| {
-            l.position.x += l.velocity.0;
-            l.position.y += l.velocity.1;
+            l.position.x += l.velocity.0 * seconds;
+            l.position.y += l.velocity.1 * seconds;
         });
-        self.time += 1;
+        self.time = (self.time as isize + seconds) as usize;
+    }
+
+    // The sky as it is (or was) at `time`
+    //
+    pub fn at(&self, time: usize) -> NightSky {
+        let mut sky = self.clone();
+        sky.step(time as isize - self.time as isize);
+        sky
+    }
+
+    // Estimate the second the lights are closest together, as the second their spread about
+    // their centre is least. The variance of their positions is quadratic in time, so its
+    // minimum has a closed form.
+    //
+    pub fn estimate(&self) -> usize {
+        let n = self.lights.len() as f64;
+        let mean =
+            |f: fn(&Light) -> isize| self.lights.iter().map(|l| f(l) as f64).sum::<f64>() / n;
+        let (px, py) = (mean(|l| l.position.x), mean(|l| l.position.y));
+        let (vx, vy) = (mean(|l| l.velocity.0), mean(|l| l.velocity.1));
+
+        let (mut pv, mut vv) = (0.0, 0.0);
+        for l in &self.lights {
+            let (dx, dy) = (l.position.x as f64 - px, l.position.y as f64 - py);
+            let (dvx, dvy) = (l.velocity.0 as f64 - vx, l.velocity.1 as f64 - vy);
+            pv += dx * dvx + dy * dvy;
+            vv += dvx * dvx + dvy * dvy;
+        }
+
+        if vv == 0.0 {
+            // The lights drift as one, so are never any closer than they are now
+            return self.time;
+        }
+        (self.time as f64 - pv / vv).round().max(0.0) as usize
+    }
+
+    // The second the lights' bounding box is smallest, found by searching outwards from the
+    // estimate
+    //
+    pub fn convergence_time(&self) -> usize {
+        let area = |time: usize| self.at(time).area();
+        let mut time = self.estimate();
+        let mut best = area(time);
+
+        loop {
+            let earlier = if time > 0 { area(time - 1) } else { usize::MAX };
+            let later = area(time + 1);
+
+            if earlier < best && earlier <= later {
+                time -= 1;
+                best = earlier;
+            } else if later < best {
+                time += 1;
+                best = later;
+            } else {
+                return time;
+            }
+        }
+    }
+
+    // Jump to the second the lights' bounding box is smallest
+    //
+    pub fn converge(&mut self) {
+        *self = self.at(self.convergence_time());
     }
 
-    fn display(&self) -> String {
+    pub fn display(&self) -> String {
         let b = self.bounds();
         let mut grid =
             vec![vec![b'.'; (b.maxx - b.minx + 1) as usize]; (b.maxy - b.miny + 1) as usize];
@@ -129,6 +209,61 @@ impl NightSky {
         buffer
     }
 
+    // Draw the sky as an SVG image, with a square for each light
+    //
+    pub fn to_svg(&self) -> String {
+        let mut svg = self.bounds().svg_open();
+        for l in &self.lights {
+            svg.push_str(&format!(
+                "    <rect x=\"{}\" y=\"{}\" width=\"1\" height=\"1\"/>\n",
+                l.position.x, l.position.y
+            ));
+        }
+        svg.push_str("  </g>\n</svg>\n");
+        svg
+    }
+
+    // Draw the lights moving from where they are at `start` to where they are at `end` as an
+    // animated SVG image, spending `tick_secs` on each second of sky time and then holding the
+    // final frame
+    //
+    pub fn to_animated_svg(&self, start: usize, end: usize, tick_secs: f64) -> String {
+        assert!(start <= end, "the animation must run forward in time");
+        let (first, last) = (self.at(start), self.at(end));
+
+        // Lights move in straight lines, so never stray outside where they start and end
+        let mut svg = first.bounds().union(&last.bounds()).svg_open();
+        let dur = (end - start) as f64 * tick_secs;
+
+        for (a, b) in first.lights.iter().zip(&last.lights) {
+            svg.push_str(&format!(
+                "    <rect x=\"{}\" y=\"{}\" width=\"1\" height=\"1\">\n",
+                a.position.x, a.position.y
+            ));
+            if dur > 0.0 {
+                for (attribute, from, to) in &[
+                    ("x", a.position.x, b.position.x),
+                    ("y", a.position.y, b.position.y),
+                ] {
+                    svg.push_str(&format!(
+                        "      <animate attributeName=\"{}\" from=\"{}\" to=\"{}\" dur=\"{}s\" fill=\"freeze\"/>\n",
+                        attribute, from, to, dur
+                    ));
+                }
+            }
+            svg.push_str("    </rect>\n");
+        }
+
+        svg.push_str("  </g>\n</svg>\n");
+        svg
+    }
+
+    // Read the message the lights currently spell out
+    //
+    pub fn message(&self) -> Result<String, OcrError> {
+        ocr::read_str(&self.display())
+    }
+
     //Returns top left point, and bottom right point
     fn bounds(&self) -> Bounds {
         let maxy = self
@@ -168,21 +303,54 @@ impl NightSky {
         }
     }
 
-    fn dimensions(&self) -> (usize, usize) {
+    pub fn dimensions(&self) -> (usize, usize) {
         let b = self.bounds();
         (
             (b.maxx - b.minx + 1) as usize,
             (b.maxy - b.miny + 1) as usize,
         )
     }
+
+    // The area of the lights' bounding box
+    //
+    pub fn area(&self) -> usize {
+        let (w, h) = self.dimensions();
+        w * h
+    }
 }
 
 impl std::convert::AsRef<NightSky> for NightSky {
     fn as_ref(&self) -> &NightSky {
-        &self
+        self
     }
 }
 
+#[aoc_generator(day10)]
+pub fn input_sky(input: &str) -> NightSky {
+    NightSky::new(input)
+}
+
+#[aoc(day10, part1)]
+pub fn part1(input: &NightSky) -> String {
+    let mut sky = input.clone();
+    sky.converge();
+    format!("\n{}", sky.display())
+}
+
+#[aoc(day10, part1, ocr)]
+pub fn part1_ocr(input: &NightSky) -> Result<String, OcrError> {
+    let mut sky = input.clone();
+    sky.converge();
+    sky.message()
+}
+
+#[aoc(day10, part2)]
+pub fn part2(input: &NightSky) -> usize {
+    let mut sky = input.clone();
+    sky.converge();
+    sky.time
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -193,6 +361,38 @@ mod tests {
                              position=< 6, 10> velocity=<-2, -1>\n\
                              position=< 2, -4> velocity=< 2,  2>";
 
+    static SAMPLE_STR: &str = "position=< 9,  1> velocity=< 0,  2>
+position=< 7,  0> velocity=<-1,  0>
+position=< 3, -2> velocity=<-1,  1>
+position=< 6, 10> velocity=<-2, -1>
+position=< 2, -4> velocity=< 2,  2>
+position=<-6, 10> velocity=< 2, -2>
+position=< 1,  8> velocity=< 1, -1>
+position=< 1,  7> velocity=< 1,  0>
+position=<-3, 11> velocity=< 1, -2>
+position=< 7,  6> velocity=<-1, -1>
+position=<-2,  3> velocity=< 1,  0>
+position=<-4,  3> velocity=< 2,  0>
+position=<10, -3> velocity=<-1,  1>
+position=< 5, 11> velocity=< 1, -2>
+position=< 4,  7> velocity=< 0, -1>
+position=< 8, -2> velocity=< 0,  1>
+position=<15,  0> velocity=<-2,  0>
+position=< 1,  6> velocity=< 1,  0>
+position=< 8,  9> velocity=< 0, -1>
+position=< 3,  3> velocity=<-1,  1>
+position=< 0,  5> velocity=< 0, -1>
+position=<-2,  2> velocity=< 2,  0>
+position=< 5, -2> velocity=< 1,  2>
+position=< 1,  4> velocity=< 2,  1>
+position=<-2,  7> velocity=< 2, -2>
+position=< 3,  6> velocity=<-1, -1>
+position=< 5,  0> velocity=< 1,  0>
+position=<-6,  0> velocity=< 2,  0>
+position=< 5,  9> velocity=< 1, -2>
+position=<14,  7> velocity=<-2,  0>
+position=<-3,  6> velocity=< 2, -1>";
+
     #[test]
     fn day10_grok_input() {
         let lights = vec![
@@ -221,4 +421,109 @@ mod tests {
         assert_eq!(NightSky::new(TEST_STR), NightSky { lights, time: 0 });
     }
 
+    #[test]
+    fn sample1() {
+        let expected = "\n\
+                        #...#..###\n\
+                        #...#...#.\n\
+                        #...#...#.\n\
+                        #####...#.\n\
+                        #...#...#.\n\
+                        #...#...#.\n\
+                        #...#...#.\n\
+                        #...#..###\n";
+
+        assert_eq!(part1(&input_sky(SAMPLE_STR)), expected);
+    }
+
+    #[test]
+    fn sample2() {
+        assert_eq!(part2(&input_sky(SAMPLE_STR)), 3);
+    }
+
+    #[test]
+    fn sample1_ocr() {
+        // The sample's letters are 8 rows tall, which no font uses
+        assert_eq!(
+            part1_ocr(&input_sky(SAMPLE_STR)),
+            Err(OcrError::UnsupportedHeight(8))
+        );
+    }
+
+    #[test]
+    fn jump() {
+        let sky = input_sky(SAMPLE_STR);
+        let mut ticked = sky.clone();
+        for _ in 0..4 {
+            ticked.tick();
+        }
+
+        assert_eq!(sky.at(4), ticked);
+        assert_eq!(ticked.at(0), sky);
+        assert_eq!(sky.estimate(), 3);
+        assert_eq!(ticked.convergence_time(), 3);
+    }
+
+    #[test]
+    fn converge_far_future() {
+        // The sample, wound back a billion seconds
+        let mut sky = input_sky(SAMPLE_STR);
+        let rewind = 1_000_000_000;
+        for l in &mut sky.lights {
+            l.position.x -= l.velocity.0 * rewind;
+            l.position.y -= l.velocity.1 * rewind;
+        }
+
+        assert_eq!(part2(&sky), rewind as usize + 3);
+        assert_eq!(part1(&sky), part1(&input_sky(SAMPLE_STR)));
+    }
+
+    #[test]
+    fn svg() {
+        let sky = input_sky(
+            "position=< 1,  2> velocity=< 1,  0>\n\
+             position=< 3, -1> velocity=<-1,  1>",
+        );
+        let expected = "\
+            <svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 -2 5 6\" width=\"40\" height=\"48\">\n\
+            \x20 <rect x=\"0\" y=\"-2\" width=\"5\" height=\"6\" fill=\"black\"/>\n\
+            \x20 <g fill=\"gold\">\n\
+            \x20   <rect x=\"1\" y=\"2\" width=\"1\" height=\"1\"/>\n\
+            \x20   <rect x=\"3\" y=\"-1\" width=\"1\" height=\"1\"/>\n\
+            \x20 </g>\n\
+            </svg>\n";
+
+        assert_eq!(sky.to_svg(), expected);
+    }
+
+    #[test]
+    fn animated_svg() {
+        let sky = input_sky(
+            "position=< 1,  2> velocity=< 1,  0>\n\
+             position=< 3, -1> velocity=<-1,  1>",
+        );
+        let svg = sky.to_animated_svg(1, 3, 0.5);
+
+        // Covers both lights from 1s (2,2 and 2,0) to 3s (4,2 and 0,2)
+        assert!(svg.contains("viewBox=\"-1 -1 7 5\""));
+        assert!(svg.contains(
+            "<rect x=\"2\" y=\"0\" width=\"1\" height=\"1\">\n\
+             \x20     <animate attributeName=\"x\" from=\"2\" to=\"0\" dur=\"1s\" fill=\"freeze\"/>\n\
+             \x20     <animate attributeName=\"y\" from=\"0\" to=\"2\" dur=\"1s\" fill=\"freeze\"/>\n"
+        ));
+        assert_eq!(svg.matches("<animate ").count(), 4);
+        assert_eq!(
+            sky.to_animated_svg(2, 2, 0.5).matches("<animate ").count(),
+            0
+        );
+    }
+
+    #[test]
+    fn converged_sky_still() {
+        // Lights that never move are already as close together as they will get
+        let mut sky = input_sky("position=< 1,  2> velocity=< 0,  0>");
+        sky.converge();
+        assert_eq!(sky.time(), 0);
+        assert_eq!(sky.display(), "#\n");
+    }
 }
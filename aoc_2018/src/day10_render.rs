@@ -0,0 +1,48 @@
+// Day 10: The Stars Align (frames)
+//
+// Prints the frames either side of the second the lights align, for when the message needs
+// a second look, and optionally exports them (and an animation of the message forming) as
+// SVG images. Run with: `cargo run --bin day10_render [frames either side] [svg directory]`
+//
+extern crate aoc_2018;
+
+use aoc_2018::day10::NightSky;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+// How long each second of sky time takes in the animation
+const TICK_SECS: f64 = 0.25;
+
+fn main() {
+    let path = "input/2018/day10.txt";
+    let contents = match fs::read_to_string(path) {
+        Err(e) => panic!("Couldn't open {}: {}", path, e),
+        Ok(contents) => contents,
+    };
+
+    let spread: usize = env::args().nth(1).map_or(1, |s| s.parse().unwrap());
+    let output = env::args().nth(2);
+
+    let sky = NightSky::new(contents.trim());
+    let aligned = sky.convergence_time();
+    let (start, end) = (aligned.saturating_sub(spread), aligned + spread);
+
+    for time in start..=end {
+        let frame = sky.at(time);
+        println!("t @ {}s", time);
+        println!("{}", frame.display().trim());
+
+        if let Some(dir) = &output {
+            let file = Path::new(dir).join(format!("day10-{}.svg", time));
+            fs::write(&file, frame.to_svg()).unwrap();
+        }
+    }
+
+    if let Some(dir) = &output {
+        // The message forming, rather than forming and then scattering again
+        let file = Path::new(dir).join("day10.svg");
+        fs::write(&file, sky.to_animated_svg(start, aligned, TICK_SECS)).unwrap();
+        println!("Wrote frames and {}", file.display());
+    }
+}
@@ -1,8 +1,13 @@
 //Day 11: Chronal Charge
 //
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::fmt;
+use std::ops::RangeInclusive;
 
-#[derive(Debug, Default, PartialEq, Eq)]
+const GRID_SIZE: usize = 300;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct PowerCell(usize, usize, usize); // Of the format (x, y, size)
 
 impl fmt::Display for PowerCell {
@@ -11,24 +16,167 @@ impl fmt::Display for PowerCell {
     }
 }
 
-pub fn optimal_power(grid: &[[isize; 300]; 300], size: usize) -> (isize, PowerCell) {
-    let mut max: isize = grid[0][0];
-    let mut max_fuel_cell = PowerCell(1, 1, 1); // Indexing starts at 1
+impl PowerCell {
+    // Whether the two squares share any fuel cells
+    //
+    pub fn overlaps(&self, other: &PowerCell) -> bool {
+        let PowerCell(x, y, size) = *self;
+        let PowerCell(ox, oy, osize) = *other;
+        x < ox + osize && ox < x + size && y < oy + osize && oy < y + size
+    }
+}
 
-    let bound = if size == 300 { 1 } else { (300 - size) % 300 };
+// The power level of every fuel cell in a grid, which need not be square
+//
+pub struct PowerGrid {
+    cells: Vec<isize>,
+    width: usize,
+    height: usize,
+}
 
-    for (x, y) in iproduct!(0..bound, 0..bound) {
-        let fuel_square_power: isize = iproduct!(0..size, 0..size)
-            .map(|(i, j)| grid[x + i][y + j])
-            .sum();
+impl PowerGrid {
+    pub fn new(serial_num: isize, width: usize, height: usize) -> PowerGrid {
+        let cells = iproduct!(0..height, 0..width)
+            .map(|(y, x)| cell_power(x + 1, y + 1, serial_num)) // Account for index starting at 1
+            .collect();
 
-        if fuel_square_power > max {
-            max = fuel_square_power;
-            max_fuel_cell = PowerCell(x + 1, y + 1, size); // Indexing starts at 1
+        PowerGrid {
+            cells,
+            width,
+            height,
         }
     }
 
-    (max, max_fuel_cell)
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // The power level of the cell at (x, y), indexing from 0
+    //
+    pub fn power(&self, x: usize, y: usize) -> isize {
+        self.cells[y * self.width + x]
+    }
+}
+
+pub fn optimal_power(grid: &PowerGrid, size: usize) -> (isize, PowerCell) {
+    SummedArea::new(grid).optimal_power(size)
+}
+
+// A summed-area table of the power grid. Each entry is the total power of every cell above and
+// to the left of it, so the power of any square can be found from just four entries.
+//
+pub struct SummedArea {
+    sums: Vec<isize>,
+    width: usize, // The size of the grid; the table has an extra row and column of zeros
+    height: usize,
+}
+
+impl SummedArea {
+    pub fn new(grid: &PowerGrid) -> SummedArea {
+        let (width, height) = (grid.width, grid.height);
+        let stride = width + 1;
+        let mut sums = vec![0; stride * (height + 1)];
+
+        for (y, x) in iproduct!(0..height, 0..width) {
+            sums[(y + 1) * stride + x + 1] =
+                grid.power(x, y) + sums[y * stride + x + 1] + sums[(y + 1) * stride + x]
+                    - sums[y * stride + x];
+        }
+
+        SummedArea {
+            sums,
+            width,
+            height,
+        }
+    }
+
+    // The total power of the square of `size` with its top left cell at (x, y), indexing from 0
+    //
+    pub fn square(&self, x: usize, y: usize, size: usize) -> isize {
+        let stride = self.width + 1;
+        let (x2, y2) = (x + size, y + size);
+        self.sums[y2 * stride + x2] - self.sums[y2 * stride + x] - self.sums[y * stride + x2]
+            + self.sums[y * stride + x]
+    }
+
+    // Every square with a size in `sizes` that fits the grid, and its power. Squares come
+    // smallest first, then scanning from the left.
+    //
+    pub fn squares(
+        &self,
+        sizes: RangeInclusive<usize>,
+    ) -> impl Iterator<Item = (isize, PowerCell)> + '_ {
+        let largest = self.width.min(self.height);
+        let sizes = (*sizes.start()).max(1)..=(*sizes.end()).min(largest);
+
+        sizes.flat_map(move |size| {
+            iproduct!(0..=self.width - size, 0..=self.height - size).map(move |(x, y)| {
+                let cell = PowerCell(x + 1, y + 1, size); // Indexing starts at 1
+                (self.square(x, y, size), cell)
+            })
+        })
+    }
+
+    // The most powerful square with a size in `sizes`, and its power. Ties go to the first
+    // square found.
+    //
+    pub fn best(&self, sizes: RangeInclusive<usize>) -> Option<(isize, PowerCell)> {
+        self.squares(sizes)
+            .fold(None, |best, (power, cell)| match best {
+                Some((max, _)) if max >= power => best,
+                _ => Some((power, cell)),
+            })
+    }
+
+    pub fn optimal_power(&self, size: usize) -> (isize, PowerCell) {
+        self.best(size..=size).expect("square larger than the grid")
+    }
+
+    // The `k` most powerful squares with a size in `sizes`, most powerful first
+    //
+    pub fn top(&self, k: usize, sizes: RangeInclusive<usize>) -> Vec<(isize, PowerCell)> {
+        // Keep the best k seen so far in a min heap, ranking ties by the order they were found
+        let mut heap = BinaryHeap::with_capacity(k + 1);
+        for (order, (power, cell)) in self.squares(sizes).enumerate() {
+            heap.push(Reverse((power, Reverse(order), cell)));
+            if heap.len() > k {
+                heap.pop();
+            }
+        }
+
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((power, _, cell))| (power, cell))
+            .collect()
+    }
+
+    // The `k` most powerful squares with a size in `sizes` that share no fuel cells, picking
+    // the most powerful square left each time. There may be fewer than `k` if the grid runs out.
+    //
+    pub fn top_disjoint(&self, k: usize, sizes: RangeInclusive<usize>) -> Vec<(isize, PowerCell)> {
+        let mut picked: Vec<(isize, PowerCell)> = Vec::with_capacity(k);
+
+        while picked.len() < k {
+            let next = self
+                .squares(sizes.clone())
+                .filter(|(_, cell)| picked.iter().all(|(_, p)| !p.overlaps(cell)))
+                .fold(None, |best, (power, cell)| match best {
+                    Some((max, _)) if max >= power => best,
+                    _ => Some((power, cell)),
+                });
+
+            match next {
+                Some(square) => picked.push(square),
+                None => break,
+            }
+        }
+
+        picked
+    }
 }
 
 #[aoc(day11, part1)]
@@ -42,20 +190,9 @@ pub fn part1(input: &str) -> PowerCell {
 #[aoc(day11, part2)]
 pub fn part2(input: &str) -> PowerCell {
     let serial_num: isize = input.lines().next().unwrap().parse().unwrap();
-    let grid = power_grid(serial_num);
-
-    let mut max: isize = grid[0][0];
-    let mut max_fuel_cell = PowerCell(1, 1, 1); // Indexing starts at 1
+    let table = SummedArea::new(&power_grid(serial_num));
 
-    for size in 1..=300 {
-        let optimal = optimal_power(&grid, size);
-        if optimal.0 > max {
-            max = optimal.0;
-            max_fuel_cell = optimal.1;
-        }
-    }
-
-    max_fuel_cell
+    table.best(1..=GRID_SIZE).unwrap().1
 }
 
 fn cell_power(x: usize, y: usize, serial_num: isize) -> isize {
@@ -67,14 +204,8 @@ fn cell_power(x: usize, y: usize, serial_num: isize) -> isize {
     power - 5
 }
 
-fn power_grid(serial_num: isize) -> [[isize; 300]; 300] {
-    let mut grid = [[0; 300]; 300];
-
-    for (x, y) in iproduct!(0..300, 0..300) {
-        grid[x][y] = cell_power(x + 1, y + 1, serial_num); // Account for index starting at 1
-    }
-
-    grid
+fn power_grid(serial_num: isize) -> PowerGrid {
+    PowerGrid::new(serial_num, GRID_SIZE, GRID_SIZE)
 }
 
 #[cfg(test)]
@@ -89,13 +220,108 @@ mod tests {
 
     #[test]
     fn sample2() {
-        //... TOO SLOW ... :(
-        // assert_eq!(part2("18"), PowerCell(90,296,16));
-        // assert_eq!(part2("42"), PowerCell(232,251,12));
+        assert_eq!(part2("18"), PowerCell(90, 269, 16));
+        assert_eq!(part2("42"), PowerCell(232, 251, 12));
     }
 
     #[test]
     fn power_calc() {
         assert_eq!(cell_power(3, 5, 8), 4);
     }
+
+    #[test]
+    fn summed_area() {
+        let grid = power_grid(18);
+        let table = SummedArea::new(&grid);
+
+        for &(x, y, size) in &[
+            (0, 0, 1),
+            (32, 44, 3),
+            (89, 268, 16),
+            (0, 0, 300),
+            (299, 299, 1),
+        ] {
+            let brute: isize = iproduct!(0..size, 0..size)
+                .map(|(i, j)| grid.power(x + i, y + j))
+                .sum();
+            assert_eq!(table.square(x, y, size), brute);
+        }
+        assert_eq!(table.optimal_power(3), (29, PowerCell(33, 45, 3)));
+        assert_eq!(table.optimal_power(16), (113, PowerCell(90, 269, 16)));
+    }
+
+    #[test]
+    fn rectangular_grid() {
+        let grid = PowerGrid::new(18, 40, 7);
+        let table = SummedArea::new(&grid);
+
+        assert_eq!((grid.width(), grid.height()), (40, 7));
+        assert_eq!(grid.power(2, 4), cell_power(3, 5, 18));
+        assert_eq!(
+            table.squares(1..=10).count(),
+            40 * 7 + 39 * 6 + 38 * 5 + 37 * 4 + 36 * 3 + 35 * 2 + 34
+        );
+        assert_eq!(table.best(8..=10), None);
+
+        let brute = iproduct!(1..=7, 0..40, 0..7)
+            .filter(|&(size, x, y)| x + size <= 40 && y + size <= 7)
+            .map(|(size, x, y)| {
+                iproduct!(0..size, 0..size)
+                    .map(|(i, j)| grid.power(x + i, y + j))
+                    .sum::<isize>()
+            })
+            .max();
+        assert_eq!(table.best(1..=7).map(|(power, _)| power), brute);
+    }
+
+    #[test]
+    fn top_squares() {
+        let table = SummedArea::new(&power_grid(18));
+        let top = table.top(5, 3..=3);
+
+        assert_eq!(top.len(), 5);
+        assert_eq!(top[0], (29, PowerCell(33, 45, 3)));
+        assert!(top.windows(2).all(|w| w[0].0 >= w[1].0));
+
+        // Every other 3x3 square is at most as powerful as the last of the five
+        let mut powers: Vec<isize> = table.squares(3..=3).map(|(p, _)| p).collect();
+        powers.sort_by(|a, b| b.cmp(a));
+        assert_eq!(
+            top.iter().map(|(p, _)| *p).collect::<Vec<_>>(),
+            &powers[..5]
+        );
+
+        assert_eq!(table.top(1, 1..=20), vec![(113, PowerCell(90, 269, 16))]);
+        assert!(table.top(0, 1..=3).is_empty());
+    }
+
+    #[test]
+    fn top_disjoint_squares() {
+        let table = SummedArea::new(&power_grid(42));
+        let top = table.top_disjoint(4, 3..=3);
+
+        assert_eq!(top[0], (30, PowerCell(21, 61, 3)));
+        for (i, (_, a)) in top.iter().enumerate() {
+            assert!(top[i + 1..].iter().all(|(_, b)| !a.overlaps(b)));
+        }
+        assert!(table
+            .top(4, 3..=3)
+            .iter()
+            .any(|(_, c)| c.overlaps(&top[0].1) && *c != top[0].1));
+
+        // A 2x2 grid has room for just one 2x2 square, or four 1x1 squares
+        let small = SummedArea::new(&PowerGrid::new(18, 2, 2));
+        assert_eq!(small.top_disjoint(3, 2..=2).len(), 1);
+        assert_eq!(small.top_disjoint(5, 1..=1).len(), 4);
+    }
+
+    #[test]
+    fn overlap() {
+        let cell = PowerCell(5, 5, 3);
+
+        assert!(cell.overlaps(&PowerCell(7, 7, 1)));
+        assert!(cell.overlaps(&PowerCell(1, 1, 5)));
+        assert!(!cell.overlaps(&PowerCell(8, 5, 3)));
+        assert!(!cell.overlaps(&PowerCell(1, 1, 4)));
+    }
 }
@@ -2,10 +2,9 @@
 //
 // Run with `RUST_LOG=debug` to get debug statments for garden visualisation.
 //
-use std::collections::BTreeMap;
 use std::fmt;
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub enum Pot {
     Plant,
     Empty,
@@ -26,6 +25,14 @@ impl Pot {
     fn has_plant(&self) -> bool {
         self == &Pot::Plant
     }
+
+    // The pots around (and including) a pot as a 5 bit number, leftmost pot highest
+    //
+    fn pattern(configuration: &[Pot]) -> usize {
+        configuration
+            .iter()
+            .fold(0, |pattern, p| (pattern << 1) | p.has_plant() as usize)
+    }
 }
 
 impl fmt::Display for Pot {
@@ -54,88 +61,134 @@ impl fmt::Display for Note {
     }
 }
 
+// A run of pots as a bitset, starting from pot number `start`. Pots outside the run are empty.
+//
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+struct Pots {
+    bits: Vec<u64>,
+    start: isize,
+    len: usize,
+}
+
+impl Pots {
+    fn new(start: isize, len: usize) -> Pots {
+        Pots {
+            bits: vec![0; len.div_ceil(64)],
+            start,
+            len,
+        }
+    }
+
+    fn end(&self) -> isize {
+        self.start + self.len as isize
+    }
+
+    fn has_plant(&self, pot: isize) -> bool {
+        if pot < self.start || pot >= self.end() {
+            return false;
+        }
+        let i = (pot - self.start) as usize;
+        self.bits[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    fn plant(&mut self, pot: isize) {
+        let i = (pot - self.start) as usize;
+        self.bits[i / 64] |= 1 << (i % 64);
+    }
+
+    // The numbers of the pots with plants, in order
+    //
+    fn plants(&self) -> impl Iterator<Item = isize> + '_ {
+        self.bits.iter().enumerate().flat_map(move |(w, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some(self.start + (w * 64 + bit) as isize)
+            })
+        })
+    }
+}
+
 #[derive(Default, PartialEq, Eq, Clone)]
 pub struct Garden {
-    pots: BTreeMap<isize, Pot>,
+    pots: Pots,
     generation: usize,
     notes: Vec<Note>,
+    rules: u32, // Bit n is set if the pots around a pot matching pattern n grow a plant
 }
 
 impl std::convert::AsRef<Garden> for Garden {
     fn as_ref(&self) -> &Garden {
-        &self
+        self
     }
 }
 
 impl Garden {
     fn grow(&mut self) {
-        self.expand(); // Make sure there is some room to grow!
-        let snapshot: Vec<Pot> = self.pots.iter().map(|(_, v)| v.clone()).collect();
-
-        for (i, (k, v)) in self
-            .pots
-            .iter_mut()
-            .enumerate()
-            .skip(2)
-            .take(snapshot.len() - 4)
-        {
-            if let Some(rule) = self
-                .notes
-                .iter()
-                .find(|n| n.configuration == &snapshot[i - 2..=i + 2])
-            {
-                *v = rule.result.clone();
-            } else {
-                *v = Pot::Empty;
-                warn!("Gen({}) no rule found for pot {}", self.generation, k);
+        let (start, end) = self.expand(); // Make sure there is some room to grow!
+        let old = &self.pots;
+        let mut pots = Pots::new(start, (end - start) as usize);
+
+        // The two pots at each end of the line have nothing to compare against past them, so
+        // stay as they are
+        for pot in (start..start + 2).chain(end - 2..end) {
+            if old.has_plant(pot) {
+                pots.plant(pot);
+            }
+        }
+
+        // Slide the five pot pattern along the line, looking each pot's rule up in the table
+        let mut pattern =
+            (start..start + 4).fold(0, |p, pot| (p << 1) | old.has_plant(pot) as usize);
+        for pot in start + 2..end - 2 {
+            pattern = ((pattern << 1) | old.has_plant(pot + 2) as usize) & 0b11111;
+            if self.rules & (1 << pattern) != 0 {
+                pots.plant(pot);
             }
         }
 
+        self.pots = pots;
         self.generation += 1;
     }
 
-    fn expand(&mut self) {
-        // Checks if the edges of the pot line needs to be expanded
-        // to allow room for growth
-        let expand_f = self.pots.iter().take(5).any(|(_, p)| p == &Pot::Plant);
-        let expand_b = self
-            .pots
-            .iter()
-            .rev()
-            .take(5)
-            .any(|(_, p)| p == &Pot::Plant);
-
-        if expand_f {
-            let start = *self.pots.iter().next().unwrap().0;
-            (1..=5).for_each(|i| {
-                self.pots.insert(start - i, Pot::Empty);
-            });
-        }
+    // Checks if the edges of the pot line needs to be expanded to allow room for growth,
+    // returning the range of pots to grow
+    //
+    fn expand(&self) -> (isize, isize) {
+        let (mut start, mut end) = (self.pots.start, self.pots.end());
+        let near = |from: isize, to: isize| (from..to).any(|pot| self.pots.has_plant(pot));
 
-        if expand_b {
-            let end = *self.pots.iter().rev().next().unwrap().0;
-            (1..=5).for_each(|i| {
-                self.pots.insert(end + i, Pot::Empty);
-            });
+        if near(start, (start + 5).min(end)) {
+            start -= 5;
+        }
+        if near((end - 5).max(self.pots.start), end) {
+            end += 5;
         }
+
+        (start, end)
     }
 
     fn sum_pots(&self) -> isize {
         //Add the pot numbers containing plants togher
-        self.pots
-            .iter()
-            .filter(|(_, p)| p.has_plant())
-            .map(|(k, _)| k)
-            .sum()
+        self.pots.plants().sum()
     }
 }
 
 impl fmt::Display for Garden {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}: ", self.generation)?;
-        self.pots.iter().for_each(|p| {
-            write!(f, "{}", p.1).unwrap();
-        });
+        for pot in self.pots.start..self.pots.end() {
+            let pot = if self.pots.has_plant(pot) {
+                Pot::Plant
+            } else {
+                Pot::Empty
+            };
+            write!(f, "{}", pot)?;
+        }
         Ok(())
     }
 }
@@ -155,7 +208,10 @@ impl fmt::Debug for Garden {
 pub fn input_garden(input: &str) -> Garden {
     let initial = input.lines().next().unwrap().replace("initial state: ", "");
     let tmp = Pot::parse(&initial);
-    let pots: BTreeMap<isize, Pot> = (0..tmp.len() as isize).zip(tmp.into_iter()).collect();
+    let mut pots = Pots::new(0, tmp.len());
+    for (pot, _) in (0..).zip(&tmp).filter(|(_, p)| p.has_plant()) {
+        pots.plant(pot);
+    }
 
     let notes: Vec<Note> = input
         .lines()
@@ -170,10 +226,16 @@ pub fn input_garden(input: &str) -> Garden {
         })
         .collect();
 
+    let rules = notes
+        .iter()
+        .filter(|n| n.result.has_plant())
+        .fold(0, |rules, n| rules | 1 << Pot::pattern(&n.configuration));
+
     Garden {
         pots,
         generation: 0,
         notes,
+        rules,
     }
 }
 
@@ -228,6 +290,7 @@ pub fn part2(input: &Garden) -> usize {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashSet;
 
     static TEST_STR: &str = "initial state: #..#.#..##......###...###\n\n\
                              ...## => #\n\
@@ -275,4 +338,54 @@ mod tests {
 
         assert_eq!(garden.sum_pots(), 325);
     }
+
+    #[test]
+    fn rule_table() {
+        let garden = input_garden(TEST_STR);
+
+        assert_eq!(Pot::pattern(&Pot::parse("...##")), 0b00011);
+        assert_eq!(Pot::pattern(&Pot::parse("#.#.#")), 0b10101);
+        assert_eq!(garden.rules.count_ones() as usize, garden.notes.len());
+        assert!(garden.rules & (1 << 0b00011) != 0);
+        assert!(garden.rules & (1 << 0b00000) == 0);
+    }
+
+    #[test]
+    fn long_growth() {
+        // Compare against a plain set of plants, over enough generations for the pots to span
+        // several words of the bitset
+        let mut garden = input_garden(TEST_STR);
+        let mut plants: HashSet<isize> = garden.pots.plants().collect();
+        let grows: HashSet<Vec<Pot>> = garden
+            .notes
+            .iter()
+            .filter(|n| n.result.has_plant())
+            .map(|n| n.configuration.clone())
+            .collect();
+
+        for _ in 0..300 {
+            garden.grow();
+
+            let (min, max) = (*plants.iter().min().unwrap(), *plants.iter().max().unwrap());
+            plants = (min - 2..=max + 2)
+                .filter(|p| {
+                    let around: Vec<Pot> = (p - 2..=p + 2)
+                        .map(|q| {
+                            if plants.contains(&q) {
+                                Pot::Plant
+                            } else {
+                                Pot::Empty
+                            }
+                        })
+                        .collect();
+                    grows.contains(&around)
+                })
+                .collect();
+
+            let mut expected: Vec<isize> = plants.iter().cloned().collect();
+            expected.sort();
+            assert_eq!(garden.pots.plants().collect::<Vec<_>>(), expected);
+        }
+        assert!(garden.pots.len > 128);
+    }
 }
@@ -181,7 +181,7 @@ pub struct Mine {
 
 impl std::convert::AsRef<Mine> for Mine {
     fn as_ref(&self) -> &Mine {
-        &self
+        self
     }
 }
 
@@ -217,7 +217,7 @@ impl Mine {
         //Update the carts that crashed in this tick
         for id in recently_crashed.iter() {
             if let Some(cart) = self.carts.iter_mut().find(|c| c.id == *id) {
-                (*cart).crashed = true;
+                cart.crashed = true;
             }
         }
     }
@@ -236,7 +236,7 @@ impl fmt::Display for Mine {
                     write!(f, "{}", self.map[x][y])?
                 }
             }
-            writeln!(f, "")?
+            writeln!(f)?
         }
 
         Ok(())
@@ -144,7 +144,7 @@ pub struct Cavern {
 
 impl std::convert::AsRef<Cavern> for Cavern {
     fn as_ref(&self) -> &Cavern {
-        &self
+        self
     }
 }
 
@@ -197,15 +197,10 @@ impl Cavern {
         self.adjacent(&u.pos)
             .into_iter()
             .filter_map(|adj| {
-                if let Some(target) = self
-                    .units
+                self.units
                     .iter()
                     .find(|(_, v)| v.pos == adj && u.is_enemy(v) && !v.is_dead())
-                {
-                    Some(*target.0)
-                } else {
-                    None
-                }
+                    .map(|target| *target.0)
             })
             .min_by_key(|k| self.units[k].hp)
     }
@@ -286,7 +281,7 @@ impl Cavern {
             .units
             .iter()
             .filter(|(_, v)| !v.is_dead())
-            .map(|(k, _)| k.clone())
+            .map(|(k, _)| *k)
             .collect();
         process.sort_by_key(|k| self.units[k].pos);
 
@@ -336,7 +331,7 @@ impl fmt::Display for Cavern {
                     write!(f, "{}", self.map[x][y])?
                 }
             }
-            writeln!(f, "")?
+            writeln!(f)?
         }
 
         Ok(())
@@ -33,7 +33,7 @@ pub enum Opcode {
 
 impl Opcode {
     fn op(&self, i: &[usize], r: &[usize]) -> Option<Vec<usize>> {
-        let mut o = r.clone().to_vec();
+        let mut o = r.to_vec();
         match self {
             Opcode::Addr => o[i[3]] = r.get(i[1])? + r.get(i[2])?,
             Opcode::Addi => o[i[3]] = r.get(i[1])? + i[2],
@@ -61,7 +61,7 @@ impl Opcode {
             Addr, Addi, Mulr, Muli, Banr, Bani, Borr, Bori, Setr, Seti, Gtir,
             Gtri, Gtrr, Eqir, Eqri, Eqrr,
         ];
-        OPCODES.into_iter()
+        OPCODES.iter()
     }
 }
 
@@ -89,40 +89,36 @@ pub fn input_samples(input: &str) -> Vec<Sample> {
     let mut samples = Vec::new();
     let mut lines = input.lines();
 
-    loop {
-        if let Some(line) = lines.next() {
-            if line.contains("Before") {
-                let before: Vec<usize> = line[7..]
-                    .replace(&['[', ']'][..], "")
-                    .split(',')
-                    .map(|s| s.trim())
-                    .flat_map(str::parse::<usize>)
-                    .collect();
-
-                let instruction: Vec<usize> = lines
-                    .next()
-                    .unwrap()
-                    .trim()
-                    .split(' ')
-                    .flat_map(str::parse::<usize>)
-                    .collect();
-
-                let after_line = &lines.next().unwrap()[6..];
-                let after: Vec<usize> = after_line
-                    .replace(&['[', ']'][..], "")
-                    .split(',')
-                    .map(|s| s.trim())
-                    .flat_map(str::parse::<usize>)
-                    .collect();
-
-                samples.push(Sample {
-                    instruction,
-                    before,
-                    after,
-                });
-            }
-        } else {
-            break; //We've consumed the entire string
+    while let Some(line) = lines.next() {
+        if line.contains("Before") {
+            let before: Vec<usize> = line[7..]
+                .replace(&['[', ']'][..], "")
+                .split(',')
+                .map(|s| s.trim())
+                .flat_map(str::parse::<usize>)
+                .collect();
+
+            let instruction: Vec<usize> = lines
+                .next()
+                .unwrap()
+                .trim()
+                .split(' ')
+                .flat_map(str::parse::<usize>)
+                .collect();
+
+            let after_line = &lines.next().unwrap()[6..];
+            let after: Vec<usize> = after_line
+                .replace(&['[', ']'][..], "")
+                .split(',')
+                .map(|s| s.trim())
+                .flat_map(str::parse::<usize>)
+                .collect();
+
+            samples.push(Sample {
+                instruction,
+                before,
+                after,
+            });
         }
     }
 
@@ -168,7 +164,7 @@ pub fn part2(input: &str) -> usize {
             if let Some(result) = oc.op(&s.instruction, &s.before) {
                 if result == s.after {
                     // Push the instruction code into the guess hashmap for this opcode
-                    opcode_guesses.entry(oc).or_insert(Vec::new()).push(s.instruction[0]);
+                    opcode_guesses.entry(oc).or_default().push(s.instruction[0]);
                 }
             }
         }
@@ -203,7 +199,7 @@ pub fn part2(input: &str) -> usize {
                 .map(|(&k, v)| (k, v
                     .iter()
                     .filter(|&&g| g != remove_guess)
-                    .map(|&g| g)
+                    .copied()
                     .collect::<Vec<usize>>()
                 ))
                 .collect();
@@ -257,8 +253,8 @@ mod tests {
     
     #[test]
     fn unique_vectors() {
-        assert!(is_all_same(&vec![1, 1, 1, 1, 1]));
-        assert!(!is_all_same(&vec![1, 1, 1, 1, 2]));
+        assert!(is_all_same(&[1, 1, 1, 1, 1]));
+        assert!(!is_all_same(&[1, 1, 1, 1, 2]));
     }
 
     #[test]
@@ -57,7 +57,7 @@ mod tests {
     #[test]
     fn sample1() {
         assert_eq!(
-            part1(&"abcdef\nbababc\nabbcde\nabcccd\naabcdd\nabcdee\nababab"),
+            part1("abcdef\nbababc\nabbcde\nabcccd\naabcdd\nabcdee\nababab"),
             12
         );
     }
@@ -65,7 +65,7 @@ mod tests {
     #[test]
     fn sample2() {
         assert_eq!(
-            part2(&"abcde\nfghij\nklmno\npqrst\nfguij\naxcye\nwvxyz"),
+            part2("abcde\nfghij\nklmno\npqrst\nfguij\naxcye\nwvxyz"),
             "fgij"
         );
     }
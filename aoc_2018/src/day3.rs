@@ -2,6 +2,7 @@
 //
 use regex::Regex;
 use std::collections::HashMap;
+use std::ops::Range;
 
 #[derive(Debug, PartialEq)]
 pub struct Claim {
@@ -12,6 +13,177 @@ pub struct Claim {
     h: usize,
 }
 
+impl Claim {
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn bounds(&self) -> Rect {
+        Rect {
+            x: self.x,
+            y: self.y,
+            w: self.w,
+            h: self.h,
+        }
+    }
+}
+
+// An axis-aligned rectangle of square inches, covering x..x+w and y..y+h
+//
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub w: usize,
+    pub h: usize,
+}
+
+impl Rect {
+    pub fn point(x: usize, y: usize) -> Rect {
+        Rect { x, y, w: 1, h: 1 }
+    }
+
+    // The far edges, clamped rather than overflowing for areas reaching past `usize::MAX`
+    //
+    fn right(&self) -> usize {
+        self.x.saturating_add(self.w)
+    }
+
+    fn bottom(&self) -> usize {
+        self.y.saturating_add(self.h)
+    }
+
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.right()
+            && other.x < self.right()
+            && self.y < other.bottom()
+            && other.y < self.bottom()
+    }
+
+    fn union(&self, other: &Rect) -> Rect {
+        let (x, y) = (self.x.min(other.x), self.y.min(other.y));
+        Rect {
+            x,
+            y,
+            w: self.right().max(other.right()) - x,
+            h: self.bottom().max(other.bottom()) - y,
+        }
+    }
+
+    // Doubled centre, which keeps the sort keys integral. Widened so that areas anywhere in
+    // the address space still sort exactly.
+    //
+    fn centre(&self) -> (u128, u128) {
+        (
+            2 * self.x as u128 + self.w as u128,
+            2 * self.y as u128 + self.h as u128,
+        )
+    }
+}
+
+const NODE_CAPACITY: usize = 8;
+
+#[derive(Debug)]
+struct Branch {
+    bounds: Rect,
+    children: Range<usize>,
+}
+
+// A static R-tree over a set of claims, bulk loaded with Sort-Tile-Recursive packing.
+// Level 0 branches group `leaves`, and every level above groups the one beneath it.
+//
+#[derive(Debug)]
+pub struct ClaimIndex {
+    leaves: Vec<(Rect, usize)>,
+    levels: Vec<Vec<Branch>>,
+}
+
+// Order items so that consecutive runs of NODE_CAPACITY are spatially close: sort by x,
+// cut into vertical slices, then sort each slice by y.
+//
+fn str_pack<T, F: Fn(&T) -> Rect>(items: &mut [T], bounds: F) {
+    let pages = items.len().div_ceil(NODE_CAPACITY);
+    let slices = (pages as f64).sqrt().ceil() as usize;
+    let slice_len = (slices * NODE_CAPACITY).max(1);
+
+    items.sort_by_key(|i| bounds(i).centre().0);
+    for slice in items.chunks_mut(slice_len) {
+        slice.sort_by_key(|i| bounds(i).centre().1);
+    }
+}
+
+fn group<T, F: Fn(&T) -> Rect>(items: &[T], bounds: F) -> Vec<Branch> {
+    (0..items.len())
+        .step_by(NODE_CAPACITY)
+        .map(|start| {
+            let children = start..(start + NODE_CAPACITY).min(items.len());
+            let bounds = items[children.clone()]
+                .iter()
+                .map(&bounds)
+                .reduce(|a, b| a.union(&b))
+                .unwrap();
+            Branch { bounds, children }
+        })
+        .collect()
+}
+
+impl ClaimIndex {
+    pub fn new(claims: &[Claim]) -> ClaimIndex {
+        let mut leaves: Vec<(Rect, usize)> = claims.iter().map(|c| (c.bounds(), c.id)).collect();
+        str_pack(&mut leaves, |l| l.0);
+
+        let mut levels = Vec::new();
+        let mut level = group(&leaves, |l| l.0);
+        while level.len() > 1 {
+            str_pack(&mut level, |b| b.bounds);
+            let parent = group(&level, |b| b.bounds);
+            levels.push(level);
+            level = parent;
+        }
+        if !level.is_empty() {
+            levels.push(level);
+        }
+
+        ClaimIndex { leaves, levels }
+    }
+
+    // The ids of all claims overlapping `area`, in ascending order
+    //
+    pub fn query(&self, area: &Rect) -> Vec<usize> {
+        let mut found = Vec::new();
+        let mut stack: Vec<(usize, usize)> = match self.levels.last() {
+            Some(root) => (0..root.len())
+                .map(|i| (self.levels.len() - 1, i))
+                .collect(),
+            None => return found,
+        };
+
+        while let Some((level, i)) = stack.pop() {
+            let branch = &self.levels[level][i];
+            if !branch.bounds.intersects(area) {
+                continue;
+            }
+
+            for child in branch.children.clone() {
+                if level > 0 {
+                    stack.push((level - 1, child));
+                } else if self.leaves[child].0.intersects(area) {
+                    found.push(self.leaves[child].1);
+                }
+            }
+        }
+
+        found.sort();
+        found
+    }
+
+    // The ids of all claims covering the square inch at (x, y)
+    //
+    pub fn at(&self, x: usize, y: usize) -> Vec<usize> {
+        self.query(&Rect::point(x, y))
+    }
+}
+
 // For a specific claim, find the coordinate positions it occupies
 //
 fn resolve_occupancy(claim: &Claim) -> Vec<(usize, usize)> {
@@ -131,4 +303,88 @@ mod tests {
     fn sample2() {
         assert_eq!(part2(&input_claims(TEST_STR)), 3);
     }
+
+    #[test]
+    fn index_queries() {
+        let index = ClaimIndex::new(&input_claims(TEST_STR));
+
+        assert_eq!(index.at(0, 0), vec![]);
+        assert_eq!(index.at(3, 3), vec![1, 2]);
+        assert_eq!(index.at(5, 5), vec![3]);
+        assert_eq!(index.at(7, 7), vec![]);
+        assert_eq!(
+            index.query(&Rect {
+                x: 4,
+                y: 4,
+                w: 2,
+                h: 2
+            }),
+            vec![1, 2, 3]
+        );
+        assert_eq!(
+            index.query(&Rect {
+                x: 0,
+                y: 0,
+                w: 3,
+                h: 3
+            }),
+            vec![]
+        );
+
+        // Areas reaching the end of the address space clamp, rather than overflow
+        let huge = Rect {
+            x: usize::MAX - 1,
+            y: 4,
+            w: usize::MAX,
+            h: usize::MAX,
+        };
+        assert_eq!(index.query(&huge), vec![]);
+        assert_eq!(
+            index.query(&Rect {
+                x: 5,
+                y: 5,
+                w: usize::MAX,
+                h: usize::MAX
+            }),
+            vec![3]
+        );
+        assert!(huge.intersects(&Rect::point(usize::MAX - 1, usize::MAX - 1)));
+
+        let far = input_claims(
+            "#1 @ 10000000000000000000,0: 1x1\n\
+             #2 @ 0,10000000000000000000: 1x1\n\
+             #3 @ 10000000000000000000,10000000000000000000: 1x1",
+        );
+        let far_index = ClaimIndex::new(&far);
+        assert_eq!(far_index.at(10_000_000_000_000_000_000, 0), vec![1]);
+        assert_eq!(
+            far_index.at(10_000_000_000_000_000_000, 10_000_000_000_000_000_000),
+            vec![3]
+        );
+    }
+
+    #[test]
+    fn index_matches_grid() {
+        let claims: Vec<Claim> = (0..200)
+            .map(|i| Claim {
+                id: i + 1,
+                x: (i * 37) % 90,
+                y: (i * 53) % 90,
+                w: 1 + i % 7,
+                h: 1 + i % 5,
+            })
+            .collect();
+        let index = ClaimIndex::new(&claims);
+
+        for x in 0..100 {
+            for y in 0..100 {
+                let expected: Vec<usize> = claims
+                    .iter()
+                    .filter(|c| c.bounds().intersects(&Rect::point(x, y)))
+                    .map(|c| c.id)
+                    .collect();
+                assert_eq!(index.at(x, y), expected);
+            }
+        }
+    }
 }
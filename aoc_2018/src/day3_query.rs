@@ -0,0 +1,69 @@
+// Day 3: No Matter How You Slice It (claim lookup)
+//
+// Interactive lookup of the claims covering a square inch or an area of fabric. Run with:
+// `cargo run --bin day3_query [claims file]`, then enter queries as `x,y` or `x,y: wxh`.
+//
+extern crate aoc_2018;
+extern crate regex;
+
+use aoc_2018::day3::{input_claims, ClaimIndex, Rect};
+use regex::{Captures, Regex};
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+fn main() {
+    let path = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "input/2018/day3.txt".to_string());
+    let contents = match fs::read_to_string(&path) {
+        Err(e) => panic!("Couldn't open {}: {}", path, e),
+        Ok(contents) => contents,
+    };
+
+    let index = ClaimIndex::new(&input_claims(contents.trim()));
+    let re = Regex::new(r"^(\d+),(\d+)(?::\s*(\d+)x(\d+))?$").unwrap();
+
+    let stdin = io::stdin();
+    prompt();
+    for line in stdin.lock().lines() {
+        let line = line.unwrap();
+        match re.captures(line.trim()).and_then(|caps| parse_query(&caps)) {
+            Some(area) => {
+                let ids: Vec<String> = index
+                    .query(&area)
+                    .iter()
+                    .map(|id| format!("#{}", id))
+                    .collect();
+                if ids.is_empty() {
+                    println!("no claims");
+                } else {
+                    println!("{}", ids.join(" "));
+                }
+            }
+            None => println!("expected `x,y` or `x,y: wxh`"),
+        }
+        prompt();
+    }
+}
+
+// The area a query asks about, if every number in it fits a `usize`
+//
+fn parse_query(caps: &Captures) -> Option<Rect> {
+    let field = |i: usize, default: usize| match caps.get(i) {
+        Some(c) => c.as_str().parse().ok(),
+        None => Some(default),
+    };
+
+    Some(Rect {
+        x: field(1, 0)?,
+        y: field(2, 0)?,
+        w: field(3, 1)?,
+        h: field(4, 1)?,
+    })
+}
+
+fn prompt() {
+    print!("> ");
+    io::stdout().flush().unwrap();
+}
@@ -1,9 +1,12 @@
 //Day 4: Repose Record
 //
 use chrono::prelude::*;
+use chrono::Duration;
 use regex::Regex;
-use std::collections::HashMap;
-use time::Duration;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum EventType {
@@ -64,108 +67,392 @@ fn order_schedule(schedule: &[GuardEvent]) -> HashMap<usize, Vec<GuardEvent>> {
         }
 
         if let Some(id) = current_guard {
-            mapped_schedule
-                .entry(id)
-                .or_insert_with(Vec::new)
-                .push(record.clone());
+            mapped_schedule.entry(id).or_default().push(record.clone());
         }
     }
     mapped_schedule
 }
 
-// Find the sleepiest guard given the schedule, and the time spent asleep
+// An inconsistency in the guard log, and the moment it occurred
 //
-fn sleepiest_guard(schedule: &HashMap<usize, Vec<GuardEvent>>) -> (usize, Duration) {
-    let mut sleepiest: Option<usize> = None;
-    let mut max = Duration::zero();
-
-    // Go through each guard record and calculate the time spent asleep
-    for (guard, records) in schedule.iter() {
-        let mut sleep_start: Option<NaiveDateTime> = None;
-        let mut asleep_for = Duration::zero();
-
-        for r in records {
-            match r.event {
-                EventType::Asleep => sleep_start = Some(r.dt),
-                EventType::Awake => {
-                    if let Some(start) = sleep_start {
-                        asleep_for = asleep_for + r.dt.signed_duration_since(start);
-                        sleep_start = None;
-                    }
+#[derive(Debug, Clone, PartialEq)]
+pub enum Anomaly {
+    NoGuardOnDuty { dt: NaiveDateTime },
+    WokeWithoutSleeping { guard: usize, dt: NaiveDateTime },
+    AlreadyAsleep { guard: usize, dt: NaiveDateTime },
+    AsleepAtShiftChange { guard: usize, dt: NaiveDateTime },
+    AsleepAtEndOfLog { guard: usize, dt: NaiveDateTime },
+}
+
+impl Anomaly {
+    pub fn dt(&self) -> NaiveDateTime {
+        match *self {
+            Anomaly::NoGuardOnDuty { dt }
+            | Anomaly::WokeWithoutSleeping { dt, .. }
+            | Anomaly::AlreadyAsleep { dt, .. }
+            | Anomaly::AsleepAtShiftChange { dt, .. }
+            | Anomaly::AsleepAtEndOfLog { dt, .. } => dt,
+        }
+    }
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] ", self.dt().format("%Y-%m-%d %H:%M"))?;
+        match self {
+            Anomaly::NoGuardOnDuty { .. } => write!(f, "event before any guard began a shift"),
+            Anomaly::WokeWithoutSleeping { guard, .. } => {
+                write!(f, "guard #{} woke up without falling asleep", guard)
+            }
+            Anomaly::AlreadyAsleep { guard, .. } => {
+                write!(f, "guard #{} fell asleep while already asleep", guard)
+            }
+            Anomaly::AsleepAtShiftChange { guard, .. } => {
+                write!(
+                    f,
+                    "guard #{} was still asleep when the shift changed",
+                    guard
+                )
+            }
+            Anomaly::AsleepAtEndOfLog { guard, .. } => {
+                write!(f, "guard #{} fell asleep and never woke up", guard)
+            }
+        }
+    }
+}
+
+// The error produced in strict mode when the log contains any anomalies
+//
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidLog {
+    pub anomalies: Vec<Anomaly>,
+}
+
+impl fmt::Display for InvalidLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "guard log has {} anomalies", self.anomalies.len())?;
+        for anomaly in &self.anomalies {
+            write!(f, "\n  {}", anomaly)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for InvalidLog {}
+
+// Walk the ordered list of guard events and report every inconsistency found
+//
+pub fn validate(schedule: &[GuardEvent]) -> Vec<Anomaly> {
+    let mut anomalies = Vec::new();
+    let mut current_guard: Option<usize> = None;
+    let mut asleep: Option<NaiveDateTime> = None;
+
+    for record in schedule {
+        let dt = record.dt;
+        match (record.event.clone(), current_guard) {
+            (EventType::StartedShift { id }, _) => {
+                if let (Some(guard), Some(_)) = (current_guard, asleep) {
+                    anomalies.push(Anomaly::AsleepAtShiftChange { guard, dt });
+                }
+                current_guard = Some(id);
+                asleep = None;
+            }
+            (_, None) => anomalies.push(Anomaly::NoGuardOnDuty { dt }),
+            (EventType::Asleep, Some(guard)) => {
+                if asleep.is_some() {
+                    anomalies.push(Anomaly::AlreadyAsleep { guard, dt });
+                } else {
+                    asleep = Some(dt);
+                }
+            }
+            (EventType::Awake, Some(guard)) => {
+                if asleep.is_none() {
+                    anomalies.push(Anomaly::WokeWithoutSleeping { guard, dt });
                 }
-                _ => continue, // The guard has started the next day
+                asleep = None;
             }
         }
+    }
+
+    if let (Some(guard), Some(dt)) = (current_guard, asleep) {
+        anomalies.push(Anomaly::AsleepAtEndOfLog { guard, dt });
+    }
+
+    anomalies
+}
+
+// A single night on duty for one guard, with the (half-open) intervals they slept
+//
+#[derive(Debug, Clone, PartialEq)]
+pub struct Night {
+    pub guard: usize,
+    pub date: NaiveDate,
+    pub intervals: Vec<(NaiveDateTime, NaiveDateTime)>,
+}
+
+impl Night {
+    pub fn asleep_for(&self) -> Duration {
+        self.intervals
+            .iter()
+            .fold(Duration::zero(), |acc, (start, end)| {
+                acc + end.signed_duration_since(*start)
+            })
+    }
+}
+
+const MINUTES_PER_DAY: usize = 24 * 60;
+
+// A span of `len` minutes of the day beginning at minute-of-day `start`, which may wrap
+// past midnight
+//
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Window {
+    pub start: usize,
+    pub len: usize,
+}
+
+impl Window {
+    // The puzzle only considers minutes between 00:00 and 00:59
+    pub const MIDNIGHT_HOUR: Window = Window { start: 0, len: 60 };
+    pub const FULL_DAY: Window = Window {
+        start: 0,
+        len: MINUTES_PER_DAY,
+    };
 
-        if asleep_for > max {
-            sleepiest = Some(*guard);
-            max = asleep_for;
+    pub fn new(start: NaiveTime, len: usize) -> Window {
+        Window {
+            start: minute_of_day(start),
+            len: len.min(MINUTES_PER_DAY),
         }
     }
 
-    (sleepiest.unwrap(), max)
+    // The position of a minute-of-day within the window, if it falls inside it
+    //
+    fn index(&self, minute: usize) -> Option<usize> {
+        let offset = (minute + MINUTES_PER_DAY - self.start) % MINUTES_PER_DAY;
+        if offset < self.len {
+            Some(offset)
+        } else {
+            None
+        }
+    }
+
+    // The minute-of-day at a position within the window
+    //
+    fn minute(&self, index: usize) -> usize {
+        (self.start + index) % MINUTES_PER_DAY
+    }
 }
 
-// Given a guard's schedule, find the minute of the day where he is asleep the most
+fn minute_of_day(t: NaiveTime) -> usize {
+    (t.hour() * 60 + t.minute()) as usize
+}
+
+// Every night of the log, plus a guard x minute heatmap of how often each guard was
+// asleep during each minute of the analysed window
 //
-// Returns: (the minute, and frequency)
-fn sleepiest_minute(schedule: &[GuardEvent]) -> Option<(usize, usize)> {
-    let mut sleep_start: Option<usize> = None;
-    let mut counter = HashMap::new();
+#[derive(Debug, Clone, PartialEq)]
+pub struct SleepReport {
+    window: Window,
+    nights: Vec<Night>,
+    heatmap: BTreeMap<usize, Vec<usize>>,
+}
 
-    for record in schedule {
-        match record.event {
-            EventType::Asleep => sleep_start = Some(record.dt.minute() as usize),
-            EventType::Awake => {
-                if let Some(start) = sleep_start {
-                    for i in start..record.dt.minute() as usize {
-                        *counter.entry(i).or_insert(0) += 1;
+impl SleepReport {
+    pub fn new(schedule: &[GuardEvent], window: Window) -> SleepReport {
+        let mut nights: Vec<Night> = Vec::new();
+
+        for (guard, records) in order_schedule(schedule) {
+            let mut sleep_start: Option<NaiveDateTime> = None;
+
+            for r in records {
+                match r.event {
+                    EventType::StartedShift { .. } => {
+                        // A shift that begins before midnight covers the following night
+                        let date = if r.dt.hour() >= 12 {
+                            r.dt.date().succ_opt().unwrap()
+                        } else {
+                            r.dt.date()
+                        };
+                        nights.push(Night {
+                            guard,
+                            date,
+                            intervals: Vec::new(),
+                        });
+                        sleep_start = None;
+                    }
+                    EventType::Asleep => sleep_start = Some(r.dt),
+                    EventType::Awake => {
+                        if let (Some(start), Some(night)) = (sleep_start, nights.last_mut()) {
+                            night.intervals.push((start, r.dt));
+                            sleep_start = None;
+                        }
+                    }
+                }
+            }
+        }
+        nights.sort_by_key(|n| (n.date, n.guard));
+
+        let mut heatmap = BTreeMap::new();
+        for night in &nights {
+            let minutes = heatmap
+                .entry(night.guard)
+                .or_insert_with(|| vec![0; window.len]);
+            for (start, end) in &night.intervals {
+                let mut t = *start;
+                while t < *end {
+                    if let Some(i) = window.index(minute_of_day(t.time())) {
+                        minutes[i] += 1;
                     }
-                    sleep_start = None;
+                    t += Duration::minutes(1);
                 }
             }
-            _ => continue,
+        }
+
+        SleepReport {
+            window,
+            nights,
+            heatmap,
         }
     }
 
-    if let Some(sleepiest_minute) = counter
-        .iter()
-        .max_by_key(|&(_, count)| count)
-        .map(|(val, _)| val)
-    {
-        return Some((*sleepiest_minute, counter[&sleepiest_minute]));
+    // Build the report, refusing any log that fails validation
+    //
+    pub fn strict(schedule: &[GuardEvent], window: Window) -> Result<SleepReport, InvalidLog> {
+        let anomalies = validate(schedule);
+        if anomalies.is_empty() {
+            Ok(SleepReport::new(schedule, window))
+        } else {
+            Err(InvalidLog { anomalies })
+        }
+    }
+
+    pub fn window(&self) -> Window {
+        self.window
+    }
+
+    pub fn nights(&self) -> &[Night] {
+        &self.nights
+    }
+
+    pub fn guards(&self) -> impl Iterator<Item = &usize> {
+        self.heatmap.keys()
+    }
+
+    // How many nights the guard was asleep during each minute of the window
+    //
+    pub fn minutes(&self, guard: usize) -> Option<&[usize]> {
+        self.heatmap.get(&guard).map(|m| m.as_slice())
+    }
+
+    pub fn asleep_for(&self, guard: usize) -> Duration {
+        self.nights
+            .iter()
+            .filter(|n| n.guard == guard)
+            .fold(Duration::zero(), |acc, n| acc + n.asleep_for())
+    }
+
+    // The guards who were asleep at the given moment
+    //
+    pub fn asleep_at(&self, dt: NaiveDateTime) -> Vec<usize> {
+        self.nights
+            .iter()
+            .filter(|n| {
+                n.intervals
+                    .iter()
+                    .any(|(start, end)| *start <= dt && dt < *end)
+            })
+            .map(|n| n.guard)
+            .collect()
     }
 
-    None
+    // Find the sleepiest guard, and the time spent asleep
+    //
+    pub fn sleepiest_guard(&self) -> Option<(usize, Duration)> {
+        self.guards()
+            .map(|&g| (g, self.asleep_for(g)))
+            .max_by_key(|&(_, asleep)| asleep)
+    }
+
+    // Given a guard, find the minute of the window where they are asleep the most
+    //
+    // Returns: (the minute of the day, and frequency), preferring the earliest on a tie
+    pub fn sleepiest_minute(&self, guard: usize) -> Option<(usize, usize)> {
+        self.minutes(guard)?
+            .iter()
+            .cloned()
+            .enumerate()
+            .filter(|&(_, count)| count > 0)
+            .max_by_key(|&(i, count)| (count, Reverse(i)))
+            .map(|(i, count)| (self.window.minute(i), count))
+    }
+
+    // Export the heatmap as CSV, one row per guard and one column per minute
+    //
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("guard");
+        for i in 0..self.window.len {
+            let m = self.window.minute(i);
+            csv.push_str(&format!(",{:02}:{:02}", m / 60, m % 60));
+        }
+        csv.push('\n');
+
+        for (guard, minutes) in &self.heatmap {
+            csv.push_str(&guard.to_string());
+            for count in minutes.iter() {
+                csv.push_str(&format!(",{}", count));
+            }
+            csv.push('\n');
+        }
+
+        csv
+    }
 }
 
 #[aoc(day4, part1)]
 pub fn part1(input: &[GuardEvent]) -> usize {
-    let schedule = order_schedule(input);
-    let (guard, _) = sleepiest_guard(&schedule);
+    strategy1(&SleepReport::new(input, Window::MIDNIGHT_HOUR))
+}
 
-    guard * sleepiest_minute(&schedule[&guard]).unwrap().0
+#[aoc(day4, part1, strict)]
+pub fn part1_strict(input: &[GuardEvent]) -> Result<usize, InvalidLog> {
+    Ok(strategy1(&SleepReport::strict(
+        input,
+        Window::MIDNIGHT_HOUR,
+    )?))
 }
 
 #[aoc(day4, part2)]
 pub fn part2(input: &[GuardEvent]) -> usize {
-    let schedule = order_schedule(input);
-
-    let mut freq_guard: Option<usize> = None;
-    let mut minute: Option<usize> = None;
-    let mut max_freq = 0;
-
-    for (guard, patterns) in schedule {
-        if let Some((min, frequency)) = sleepiest_minute(&patterns) {
-            if frequency > max_freq {
-                max_freq = frequency;
-                minute = Some(min);
-                freq_guard = Some(guard);
-            }
-        }
-    }
+    strategy2(&SleepReport::new(input, Window::MIDNIGHT_HOUR))
+}
+
+#[aoc(day4, part2, strict)]
+pub fn part2_strict(input: &[GuardEvent]) -> Result<usize, InvalidLog> {
+    Ok(strategy2(&SleepReport::strict(
+        input,
+        Window::MIDNIGHT_HOUR,
+    )?))
+}
 
-    freq_guard.unwrap() * minute.unwrap()
+// Strategy 1: the sleepiest guard, multiplied by the minute they are most often asleep
+//
+fn strategy1(report: &SleepReport) -> usize {
+    let (guard, _) = report.sleepiest_guard().unwrap();
+
+    guard * report.sleepiest_minute(guard).unwrap().0
+}
+
+// Strategy 2: the guard most frequently asleep on the same minute, multiplied by that minute
+//
+fn strategy2(report: &SleepReport) -> usize {
+    let (guard, (minute, _)) = report
+        .guards()
+        .filter_map(|&g| Some((g, report.sleepiest_minute(g)?)))
+        .max_by_key(|&(_, (_, frequency))| frequency)
+        .unwrap();
+
+    guard * minute
 }
 
 #[cfg(test)]
@@ -194,71 +481,122 @@ mod tests {
     fn grok_input() {
         let expected = vec![
             GuardEvent {
-                dt: NaiveDate::from_ymd(1518, 11, 1).and_hms(0, 0, 0),
+                dt: NaiveDate::from_ymd_opt(1518, 11, 1)
+                    .unwrap()
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap(),
                 event: EventType::StartedShift { id: 10 },
             },
             GuardEvent {
-                dt: NaiveDate::from_ymd(1518, 11, 1).and_hms(0, 5, 0),
+                dt: NaiveDate::from_ymd_opt(1518, 11, 1)
+                    .unwrap()
+                    .and_hms_opt(0, 5, 0)
+                    .unwrap(),
                 event: EventType::Asleep,
             },
             GuardEvent {
-                dt: NaiveDate::from_ymd(1518, 11, 1).and_hms(0, 25, 0),
+                dt: NaiveDate::from_ymd_opt(1518, 11, 1)
+                    .unwrap()
+                    .and_hms_opt(0, 25, 0)
+                    .unwrap(),
                 event: EventType::Awake,
             },
             GuardEvent {
-                dt: NaiveDate::from_ymd(1518, 11, 1).and_hms(0, 30, 0),
+                dt: NaiveDate::from_ymd_opt(1518, 11, 1)
+                    .unwrap()
+                    .and_hms_opt(0, 30, 0)
+                    .unwrap(),
                 event: EventType::Asleep,
             },
             GuardEvent {
-                dt: NaiveDate::from_ymd(1518, 11, 1).and_hms(0, 55, 0),
+                dt: NaiveDate::from_ymd_opt(1518, 11, 1)
+                    .unwrap()
+                    .and_hms_opt(0, 55, 0)
+                    .unwrap(),
                 event: EventType::Awake,
             },
             GuardEvent {
-                dt: NaiveDate::from_ymd(1518, 11, 1).and_hms(23, 58, 0),
+                dt: NaiveDate::from_ymd_opt(1518, 11, 1)
+                    .unwrap()
+                    .and_hms_opt(23, 58, 0)
+                    .unwrap(),
                 event: EventType::StartedShift { id: 99 },
             },
             GuardEvent {
-                dt: NaiveDate::from_ymd(1518, 11, 2).and_hms(0, 40, 0),
+                dt: NaiveDate::from_ymd_opt(1518, 11, 2)
+                    .unwrap()
+                    .and_hms_opt(0, 40, 0)
+                    .unwrap(),
                 event: EventType::Asleep,
             },
             GuardEvent {
-                dt: NaiveDate::from_ymd(1518, 11, 2).and_hms(0, 50, 0),
+                dt: NaiveDate::from_ymd_opt(1518, 11, 2)
+                    .unwrap()
+                    .and_hms_opt(0, 50, 0)
+                    .unwrap(),
                 event: EventType::Awake,
             },
             GuardEvent {
-                dt: NaiveDate::from_ymd(1518, 11, 3).and_hms(0, 5, 0),
+                dt: NaiveDate::from_ymd_opt(1518, 11, 3)
+                    .unwrap()
+                    .and_hms_opt(0, 5, 0)
+                    .unwrap(),
                 event: EventType::StartedShift { id: 10 },
             },
             GuardEvent {
-                dt: NaiveDate::from_ymd(1518, 11, 3).and_hms(0, 24, 0),
+                dt: NaiveDate::from_ymd_opt(1518, 11, 3)
+                    .unwrap()
+                    .and_hms_opt(0, 24, 0)
+                    .unwrap(),
                 event: EventType::Asleep,
             },
             GuardEvent {
-                dt: NaiveDate::from_ymd(1518, 11, 3).and_hms(0, 29, 0),
+                dt: NaiveDate::from_ymd_opt(1518, 11, 3)
+                    .unwrap()
+                    .and_hms_opt(0, 29, 0)
+                    .unwrap(),
                 event: EventType::Awake,
             },
             GuardEvent {
-                dt: NaiveDate::from_ymd(1518, 11, 4).and_hms(0, 2, 0),
+                dt: NaiveDate::from_ymd_opt(1518, 11, 4)
+                    .unwrap()
+                    .and_hms_opt(0, 2, 0)
+                    .unwrap(),
                 event: EventType::StartedShift { id: 99 },
             },
             GuardEvent {
-                dt: NaiveDate::from_ymd(1518, 11, 4).and_hms(0, 36, 0),
+                dt: NaiveDate::from_ymd_opt(1518, 11, 4)
+                    .unwrap()
+                    .and_hms_opt(0, 36, 0)
+                    .unwrap(),
                 event: EventType::Asleep,
             },
             GuardEvent {
-                dt: NaiveDate::from_ymd(1518, 11, 4).and_hms(0, 46, 0),
+                dt: NaiveDate::from_ymd_opt(1518, 11, 4)
+                    .unwrap()
+                    .and_hms_opt(0, 46, 0)
+                    .unwrap(),
                 event: EventType::Awake,
             },
             GuardEvent {
-                dt: NaiveDate::from_ymd(1518, 11, 5).and_hms(0, 3, 0),
+                dt: NaiveDate::from_ymd_opt(1518, 11, 5)
+                    .unwrap()
+                    .and_hms_opt(0, 3, 0)
+                    .unwrap(),
                 event: EventType::StartedShift { id: 99 },
             },
             GuardEvent {
-                dt: NaiveDate::from_ymd(1518, 11, 5).and_hms(0, 45, 0),
+                dt: NaiveDate::from_ymd_opt(1518, 11, 5)
+                    .unwrap()
+                    .and_hms_opt(0, 45, 0)
+                    .unwrap(),
                 event: EventType::Asleep,
             },
             GuardEvent {
-                dt: NaiveDate::from_ymd(1518, 11, 5).and_hms(0, 55, 0),
+                dt: NaiveDate::from_ymd_opt(1518, 11, 5)
+                    .unwrap()
+                    .and_hms_opt(0, 55, 0)
+                    .unwrap(),
                 event: EventType::Awake,
             },
         ];
@@ -268,14 +606,138 @@ mod tests {
 
     #[test]
     fn sleepy() {
-        let schedule = order_schedule(&input_schedule(TEST_STR));
-        let (guard, time_asleep) = sleepiest_guard(&schedule);
-        let (minute, freq) = sleepiest_minute(&schedule[&guard]).unwrap();
+        let report = SleepReport::new(&input_schedule(TEST_STR), Window::MIDNIGHT_HOUR);
+        let (guard, time_asleep) = report.sleepiest_guard().unwrap();
+        let (minute, freq) = report.sleepiest_minute(guard).unwrap();
 
         assert_eq!((guard, time_asleep), (10, Duration::minutes(50)));
         assert_eq!((minute, freq), (24, 2));
     }
 
+    #[test]
+    fn report() {
+        let report = SleepReport::new(&input_schedule(TEST_STR), Window::MIDNIGHT_HOUR);
+        let at = |d: u32, m: u32| {
+            NaiveDate::from_ymd_opt(1518, 11, d)
+                .unwrap()
+                .and_hms_opt(0, m, 0)
+                .unwrap()
+        };
+
+        assert_eq!(report.nights().len(), 5);
+        assert_eq!(
+            report.nights()[1].date,
+            NaiveDate::from_ymd_opt(1518, 11, 2).unwrap()
+        );
+        assert_eq!(report.nights()[1].intervals, vec![(at(2, 40), at(2, 50))]);
+        assert_eq!(report.asleep_for(99), Duration::minutes(30));
+
+        assert_eq!(report.asleep_at(at(3, 24)), vec![10]);
+        assert_eq!(report.asleep_at(at(3, 29)), vec![]);
+        assert_eq!(report.asleep_at(at(5, 45)), vec![99]);
+
+        assert_eq!(report.minutes(99).unwrap()[45], 3);
+        assert_eq!(report.minutes(10).unwrap()[24], 2);
+        assert_eq!(report.minutes(10).unwrap()[4], 0);
+    }
+
+    #[test]
+    fn heatmap_csv() {
+        let csv = SleepReport::new(&input_schedule(TEST_STR), Window::MIDNIGHT_HOUR).to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("guard,00:00,00:01,"));
+        assert!(lines[0].ends_with(",00:59"));
+        assert!(lines[1].starts_with("10,0,0,0,0,0,1,"));
+        assert_eq!(lines[2].split(',').nth(46), Some("3"));
+    }
+
+    #[test]
+    fn across_midnight() {
+        let log = "[1518-11-01 23:45] Guard #10 begins shift\n\
+                   [1518-11-01 23:58] falls asleep\n\
+                   [1518-11-02 00:05] wakes up\n\
+                   [1518-11-02 22:00] Guard #10 begins shift\n\
+                   [1518-11-02 23:59] falls asleep\n\
+                   [1518-11-03 00:01] wakes up";
+        let schedule = input_schedule(log);
+
+        let report = SleepReport::new(&schedule, Window::FULL_DAY);
+        let minutes = report.minutes(10).unwrap();
+        assert_eq!(report.asleep_for(10), Duration::minutes(9));
+        assert_eq!(minutes[23 * 60 + 57], 0);
+        assert_eq!((minutes[23 * 60 + 58], minutes[23 * 60 + 59]), (1, 2));
+        assert_eq!((minutes[0], minutes[4], minutes[5]), (2, 1, 0));
+        assert_eq!(report.sleepiest_minute(10), Some((0, 2)));
+
+        let window = Window::new(NaiveTime::from_hms_opt(23, 30, 0).unwrap(), 60);
+        let report = SleepReport::new(&schedule, window);
+        assert_eq!(report.minutes(10).unwrap().len(), 60);
+        assert_eq!(report.minutes(10).unwrap()[29], 2);
+        assert_eq!(report.sleepiest_minute(10), Some((23 * 60 + 59, 2)));
+        assert!(report.to_csv().starts_with("guard,23:30,23:31,"));
+
+        let report = SleepReport::new(&schedule, Window::MIDNIGHT_HOUR);
+        assert_eq!(report.minutes(10).unwrap()[..6], [2, 1, 1, 1, 1, 0]);
+    }
+
+    #[test]
+    fn validation() {
+        let log = "[1518-11-01 00:01] falls asleep\n\
+                   [1518-11-01 00:02] Guard #10 begins shift\n\
+                   [1518-11-01 00:05] wakes up\n\
+                   [1518-11-01 00:10] falls asleep\n\
+                   [1518-11-01 00:12] falls asleep\n\
+                   [1518-11-01 23:58] Guard #99 begins shift\n\
+                   [1518-11-02 00:40] falls asleep";
+        let at = |d: u32, h: u32, m: u32| {
+            NaiveDate::from_ymd_opt(1518, 11, d)
+                .unwrap()
+                .and_hms_opt(h, m, 0)
+                .unwrap()
+        };
+
+        assert_eq!(
+            validate(&input_schedule(log)),
+            vec![
+                Anomaly::NoGuardOnDuty { dt: at(1, 0, 1) },
+                Anomaly::WokeWithoutSleeping {
+                    guard: 10,
+                    dt: at(1, 0, 5)
+                },
+                Anomaly::AlreadyAsleep {
+                    guard: 10,
+                    dt: at(1, 0, 12)
+                },
+                Anomaly::AsleepAtShiftChange {
+                    guard: 10,
+                    dt: at(1, 23, 58)
+                },
+                Anomaly::AsleepAtEndOfLog {
+                    guard: 99,
+                    dt: at(2, 0, 40)
+                },
+            ]
+        );
+        assert_eq!(validate(&input_schedule(TEST_STR)), vec![]);
+    }
+
+    #[test]
+    fn strict() {
+        let log = "[1518-11-01 00:00] Guard #10 begins shift\n\
+                   [1518-11-01 00:05] wakes up";
+        let err = part1_strict(&input_schedule(log)).unwrap_err();
+
+        assert_eq!(err.anomalies.len(), 1);
+        assert_eq!(
+            err.to_string(),
+            "guard log has 1 anomalies\n  [1518-11-01 00:05] guard #10 woke up without falling asleep"
+        );
+        assert_eq!(part1_strict(&input_schedule(TEST_STR)), Ok(240));
+        assert_eq!(part2_strict(&input_schedule(TEST_STR)), Ok(4455));
+    }
+
     #[test]
     fn sample1() {
         assert_eq!(part1(&input_schedule(TEST_STR)), 240)
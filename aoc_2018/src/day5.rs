@@ -1,69 +1,324 @@
 //Day 5: Alchemical Reduction
 //
-fn react_polymer(polymer: &str) -> usize {
-    let search_iter = (0..26)
-        .map(|x| {
-            let c = (x + b'a') as char;
-            format!("{}{}", c, c.to_uppercase())
-        })
-        .chain((0..26).map(|x| {
-            let c = (x + b'a') as char;
-            format!("{}{}", c.to_uppercase(), c)
-        }));
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, Read};
+
+// Two adjacent units react if they are the same type, but of opposite polarity
+//
+fn reacts(a: u8, b: u8) -> bool {
+    a != b && a.eq_ignore_ascii_case(&b)
+}
+
+// A stack of the units that have not (yet) reacted. Each unit pushed either annihilates
+// the unit on top of the stack or comes to rest on it, so a polymer is reduced in one pass.
+//
+struct Reactor<T, F> {
+    stack: Vec<T>,
+    reacts: F,
+}
+
+impl<T, F: Fn(&T, &T) -> bool> Reactor<T, F> {
+    fn new(reacts: F) -> Reactor<T, F> {
+        Reactor {
+            stack: Vec::new(),
+            reacts,
+        }
+    }
+
+    fn push(&mut self, unit: T) {
+        match self.stack.last() {
+            Some(top) if (self.reacts)(top, &unit) => {
+                self.stack.pop();
+            }
+            _ => self.stack.push(unit),
+        }
+    }
+
+    fn react<I: IntoIterator<Item = T>>(mut self, units: I) -> Vec<T> {
+        units.into_iter().for_each(|u| self.push(u));
+        self.stack
+    }
+}
 
-    let mut parsed: String = polymer.to_string();
+// The puzzle's rules applied to single bytes, a fast path for `ReactionRules::default()`
+//
+fn byte_reactor() -> Reactor<u8, impl Fn(&u8, &u8) -> bool> {
+    Reactor::new(|&a, &b| reacts(a, b))
+}
+
+// Fully react a polymer, returning the units that remain
+//
+pub fn react_polymer(polymer: &str) -> String {
+    // Only ASCII letters ever react, so what remains is still valid UTF-8
+    String::from_utf8(byte_reactor().react(polymer.bytes())).unwrap()
+}
+
+// What remains of a polymer once it has been fully reacted
+//
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Reduction {
+    pub len: usize, // The number of units (characters) remaining
+    pub counts: BTreeMap<char, usize>,
+}
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+// Reduce a polymer read from `reader` a chunk at a time, so only the unreacted units are
+// ever held in memory. Whitespace (e.g. line breaks in generated input) is skipped. The
+// polymer must be UTF-8, but a character may be split across chunks: only ASCII letters
+// react, so the bytes of any other character stay together until the remains are decoded.
+//
+pub fn reduce_stream<R: Read>(reader: R) -> io::Result<Reduction> {
+    reduce_chunks(reader, CHUNK_SIZE)
+}
+
+fn reduce_chunks<R: Read>(mut reader: R, chunk_size: usize) -> io::Result<Reduction> {
+    let mut reactor = byte_reactor();
+    let mut chunk = vec![0; chunk_size];
 
     loop {
-        let search_iter = search_iter.clone();
-        let mut scanned = false;
-
-        for search in search_iter {
-            let tmp = parsed.replace(search.as_str(), "");
-            if tmp.len() < parsed.len() {
-                scanned = true;
-                parsed = tmp;
+        let n = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        chunk[..n]
+            .iter()
+            .filter(|u| !u.is_ascii_whitespace())
+            .for_each(|&u| reactor.push(u));
+    }
+
+    let remains = String::from_utf8(reactor.stack)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    let mut counts = BTreeMap::new();
+    for u in remains.chars() {
+        *counts.entry(u).or_insert(0) += 1;
+    }
+
+    Ok(Reduction {
+        len: remains.chars().count(),
+        counts,
+    })
+}
+
+// The pairs of units that annihilate when they meet, in either order. A unit may be any
+// token of one or more characters; polymers are split into units by matching the longest
+// token named in a rule, falling back to a single character.
+//
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReactionRules {
+    tokens: Vec<String>,
+    pairs: HashMap<String, HashSet<String>>,
+}
+
+impl Default for ReactionRules {
+    // The puzzle's rules: the same letter of opposite case reacts
+    fn default() -> ReactionRules {
+        (b'a'..=b'z').fold(ReactionRules::empty(), |rules, c| {
+            let unit = (c as char).to_string();
+            rules.pair(&unit, &unit.to_uppercase())
+        })
+    }
+}
+
+impl ReactionRules {
+    // A rule set in which nothing reacts, to build custom rules on
+    //
+    pub fn empty() -> ReactionRules {
+        ReactionRules {
+            tokens: Vec::new(),
+            pairs: HashMap::new(),
+        }
+    }
+
+    pub fn pair(mut self, a: &str, b: &str) -> ReactionRules {
+        for unit in &[a, b] {
+            if unit.chars().count() > 1 && !self.tokens.iter().any(|t| t == unit) {
+                self.tokens.push(unit.to_string());
             }
         }
+        self.tokens.sort_by_key(|t| std::cmp::Reverse(t.len()));
+
+        self.pairs
+            .entry(a.to_string())
+            .or_default()
+            .insert(b.to_string());
+        self.pairs
+            .entry(b.to_string())
+            .or_default()
+            .insert(a.to_string());
+        self
+    }
 
-        if !scanned {
-            break;
+    pub fn reacts(&self, a: &str, b: &str) -> bool {
+        self.pairs.get(a).is_some_and(|p| p.contains(b))
+    }
+
+    // Split a polymer into its units
+    //
+    pub fn units<'a>(&self, polymer: &'a str) -> Vec<&'a str> {
+        let mut units = Vec::new();
+        let mut rest = polymer;
+
+        while let Some(c) = rest.chars().next() {
+            let len = self
+                .tokens
+                .iter()
+                .find(|t| rest.starts_with(t.as_str()))
+                .map_or(c.len_utf8(), |t| t.len());
+            units.push(&rest[..len]);
+            rest = &rest[len..];
         }
+
+        units
+    }
+
+    // Fully react a polymer under these rules, returning the units that remain
+    //
+    pub fn react(&self, polymer: &str) -> String {
+        Reactor::new(|a: &&str, b: &&str| self.reacts(a, b))
+            .react(self.units(polymer))
+            .concat()
     }
-    parsed.len()
 }
 
 #[aoc(day5, part1)]
 pub fn part1(input: &str) -> usize {
-    react_polymer(input)
+    react_polymer(input).len()
 }
 
 #[aoc(day5, part2)]
 pub fn part2(input: &str) -> usize {
-    let remove_iter = (0..26).map(|x| (x + b'a') as char);
-
-    let mut min = input.len();
-    for r in remove_iter {
-        let scrubbed = input.replace(r, "").replace(r.to_ascii_uppercase(), "");
-        let polymers = react_polymer(&scrubbed);
-        if polymers < min {
-            min = polymers;
-        }
-    }
+    // Removing a unit type can only enable reactions, never undo ones that already happened,
+    // so each removal can start from the fully reacted polymer
+    let reduced = byte_reactor().react(input.bytes());
 
-    min
+    (b'a'..=b'z')
+        .into_par_iter()
+        .map(|r| {
+            let scrubbed = reduced
+                .iter()
+                .cloned()
+                .filter(|u| u.to_ascii_lowercase() != r);
+            byte_reactor().react(scrubbed).len()
+        })
+        .min()
+        .unwrap()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn reduced() {
+        assert_eq!(react_polymer("aA"), "");
+        assert_eq!(react_polymer("abBA"), "");
+        assert_eq!(react_polymer("abAB"), "abAB");
+        assert_eq!(react_polymer("aabAAB"), "aabAAB");
+        assert_eq!(react_polymer("dabAcCaCBAcCcaDA"), "dabCBAcaDA");
+    }
+
+    #[test]
+    fn streamed() {
+        let polymer = "dabAcCaC\nBAcCcaDA\n";
+        let expected = Reduction {
+            len: 10,
+            counts: [
+                ('A', 2),
+                ('B', 1),
+                ('C', 1),
+                ('D', 1),
+                ('a', 2),
+                ('b', 1),
+                ('c', 1),
+                ('d', 1),
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+        };
+
+        for chunk_size in 1..=polymer.len() {
+            assert_eq!(
+                reduce_chunks(polymer.as_bytes(), chunk_size).unwrap(),
+                expected
+            );
+        }
+        assert_eq!(reduce_stream(polymer.as_bytes()).unwrap(), expected);
+    }
+
+    #[test]
+    fn streamed_unicode() {
+        // `α` and `Ω` are two bytes each, so small chunks split them
+        let polymer = "αaAΩxXα\nbBΩ";
+        let expected = Reduction {
+            len: 4,
+            counts: [('Ω', 2), ('α', 2)].iter().cloned().collect(),
+        };
+
+        for chunk_size in 1..=polymer.len() {
+            assert_eq!(
+                reduce_chunks(polymer.as_bytes(), chunk_size).unwrap(),
+                expected
+            );
+        }
+
+        let invalid: &[u8] = &[b'a', 0xff, b'A'];
+        assert_eq!(
+            reduce_stream(invalid).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn streamed_large() {
+        let polymer = "abcdefghijklmnopqrstuvwxyzZYXWVUTSRQPONMLKJIHGFEDCBAq".repeat(10_000);
+        let reduction = reduce_chunks(polymer.as_bytes(), 4096).unwrap();
+
+        assert_eq!(reduction.len, 10_000);
+        assert_eq!(reduction.counts.get(&'q'), Some(&10_000));
+        assert_eq!(reduction.counts.len(), 1);
+    }
+
+    #[test]
+    fn default_rules() {
+        let rules = ReactionRules::default();
+
+        assert!(rules.reacts("a", "A") && rules.reacts("Z", "z"));
+        assert!(!rules.reacts("a", "a") && !rules.reacts("a", "B"));
+        assert_eq!(rules.react("dabAcCaCBAcCcaDA"), "dabCBAcaDA");
+        assert_eq!(ReactionRules::empty().react("aA"), "aA");
+
+        // The byte fast path runs the same rules
+        let polymer = "dabAcCaCBAcCcaDAzZqrRQx".repeat(50);
+        assert_eq!(rules.react(&polymer), react_polymer(&polymer));
+    }
+
+    #[test]
+    fn custom_rules() {
+        let rules = ReactionRules::empty()
+            .pair("α", "Ω")
+            .pair("Fe", "fE")
+            .pair("x", "x");
+
+        assert_eq!(rules.units("aFefE"), vec!["a", "Fe", "fE"]);
+        assert_eq!(rules.react("ΩαβFeαΩfE"), "β");
+        assert_eq!(rules.react("FeEf"), "FeEf");
+        assert_eq!(rules.react("xxx"), "x");
+        assert_eq!(rules.react("aA"), "aA");
+    }
+
     #[test]
     fn sample1() {
-        assert_eq!(part1(&"dabAcCaCBAcCcaDA"), 10);
+        assert_eq!(part1("dabAcCaCBAcCcaDA"), 10);
     }
 
     #[test]
     fn sample2() {
-        assert_eq!(part2(&"dabAcCaCBAcCcaDA"), 4);
+        assert_eq!(part2("dabAcCaCBAcCcaDA"), 4);
     }
 }
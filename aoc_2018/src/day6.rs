@@ -1,60 +1,439 @@
 //Day 6: Chronal Coordinates
 //
-#[derive(Clone, Debug, PartialEq, Eq)]
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Coordinate {
-    x: usize,
-    y: usize,
+    x: isize,
+    y: isize,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Bounds {
-    min_x: usize,
-    max_x: usize,
-    min_y: usize,
-    max_y: usize,
+    pub min_x: isize,
+    pub max_x: isize,
+    pub min_y: isize,
+    pub max_y: isize,
 }
 
 impl Coordinate {
     fn manhattan_dist(&self, other: &Coordinate) -> usize {
-        ((self.x as isize - other.x as isize).abs() + (self.y as isize - other.y as isize).abs())
-            as usize
+        (self.x - other.x).unsigned_abs() + (self.y - other.y).unsigned_abs()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Metric {
+    Manhattan,
+    Chebyshev,
+    Euclidean,
+}
+
+impl Metric {
+    // The distance between two coordinates. Euclidean distances are left squared, which
+    // preserves their ordering while keeping ties exact.
+    //
+    pub fn distance(self, a: &Coordinate, b: &Coordinate) -> usize {
+        let dx = (a.x - b.x).unsigned_abs();
+        let dy = (a.y - b.y).unsigned_abs();
+        match self {
+            Metric::Manhattan => a.manhattan_dist(b),
+            Metric::Chebyshev => dx.max(dy),
+            Metric::Euclidean => dx * dx + dy * dy,
+        }
     }
 }
 
+// The number of cells closest to a location, or `Infinite` if its region reaches the edge
+// of the bounding box (and so extends forever beyond it)
+//
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Area {
+    Finite(usize),
+    Infinite,
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct Grid {
     locations: Vec<Coordinate>, //All locations read from file
-    finite: Vec<Coordinate>,    //All finite locations (within the bounds of the grid)
     bounds: Bounds,             //Coordiantes that cosntrain our grid
+    metric: Metric,             //How distance to a location is measured
 }
 
 impl Grid {
-    fn new(locations: Vec<Coordinate>) -> Grid {
+    pub fn new(locations: Vec<Coordinate>, metric: Metric) -> Grid {
         //Find the bounding planes
         let min_x = locations.iter().min_by_key(|coord| coord.x).unwrap().x;
         let max_x = locations.iter().max_by_key(|coord| coord.x).unwrap().x;
         let min_y = locations.iter().min_by_key(|coord| coord.y).unwrap().y;
         let max_y = locations.iter().max_by_key(|coord| coord.y).unwrap().y;
-        //Find all the finite locations
-        let finite: Vec<Coordinate> = locations
-            .iter()
-            .cloned()
-            .filter(|coord| {
-                (coord.x > min_x && coord.x < max_x) && (coord.y > min_y && coord.y < max_y)
-            })
-            .collect();
 
         Grid {
             locations,
-            finite,
             bounds: Bounds {
                 min_x,
                 max_x,
                 min_y,
                 max_y,
             },
+            metric,
+        }
+    }
+
+    // The index of the location closest to `cell`, or None if several are equally close
+    //
+    fn closest(&self, cell: &Coordinate) -> Option<usize> {
+        let mut closest = None;
+        let mut min = usize::MAX;
+
+        for (i, location) in self.locations.iter().enumerate() {
+            let dist = self.metric.distance(location, cell);
+            if dist < min {
+                min = dist;
+                closest = Some(i);
+            } else if dist == min {
+                closest = None;
+            }
+        }
+
+        closest
+    }
+
+    fn width(&self) -> usize {
+        (self.bounds.max_x - self.bounds.min_x + 1) as usize
+    }
+
+    fn height(&self) -> usize {
+        (self.bounds.max_y - self.bounds.min_y + 1) as usize
+    }
+
+    // The position of a cell within the row-major labels of the bounding box
+    //
+    fn index(&self, cell: &Coordinate) -> Option<usize> {
+        let (x, y) = (cell.x - self.bounds.min_x, cell.y - self.bounds.min_y);
+        if x < 0 || y < 0 || x as usize >= self.width() || y as usize >= self.height() {
+            None
+        } else {
+            Some(y as usize * self.width() + x as usize)
         }
     }
+
+    // The cells a single step away from the cell at `index`, within the bounding box
+    //
+    fn neighbours(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+        let steps: &[(isize, isize)] = match self.metric {
+            Metric::Chebyshev => &[
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+                (-1, 0),
+                (1, 0),
+                (-1, 1),
+                (0, 1),
+                (1, 1),
+            ],
+            _ => &[(0, -1), (-1, 0), (1, 0), (0, 1)],
+        };
+        let x = self.bounds.min_x + (index % self.width()) as isize;
+        let y = self.bounds.min_y + (index / self.width()) as isize;
+
+        steps.iter().filter_map(move |(dx, dy)| {
+            self.index(&Coordinate {
+                x: x + dx,
+                y: y + dy,
+            })
+        })
+    }
+
+    // The closest location to every cell of the bounding box (row by row), or None for ties.
+    //
+    // Manhattan and Chebyshev distances are the length of the shortest path between
+    // neighbouring cells (4 and 8 neighbours respectively), so the labels are found with a
+    // breadth-first flood from every location at once: each wave of the flood is one step
+    // further away, and a cell reached by different labels in the same wave is a tie. This
+    // visits each cell once, however many locations there are. Euclidean distance has no
+    // such structure, so it is searched through the locations sorted by x instead (see
+    // `sweep_labels`).
+    //
+    pub fn labels(&self) -> Vec<Option<usize>> {
+        if self.metric == Metric::Euclidean {
+            return self.sweep_labels();
+        }
+
+        #[derive(Clone, Copy, PartialEq)]
+        enum Label {
+            Unvisited,
+            Closest(usize),
+            Tie,
+        }
+
+        let mut labels = vec![Label::Unvisited; self.width() * self.height()];
+        let mut wave = vec![0; labels.len()];
+        let mut frontier = Vec::new();
+
+        for (i, location) in self.locations.iter().enumerate() {
+            let idx = self.index(location).unwrap();
+            labels[idx] = match labels[idx] {
+                Label::Unvisited => {
+                    frontier.push(idx);
+                    Label::Closest(i)
+                }
+                _ => Label::Tie, // Two locations share the same cell
+            };
+        }
+
+        let mut distance = 0;
+        while !frontier.is_empty() {
+            distance += 1;
+            let mut next = Vec::new();
+
+            for &idx in &frontier {
+                let label = labels[idx];
+                for n in self.neighbours(idx) {
+                    if labels[n] == Label::Unvisited {
+                        labels[n] = label;
+                        wave[n] = distance;
+                        next.push(n);
+                    } else if wave[n] == distance && labels[n] != label {
+                        labels[n] = Label::Tie;
+                    }
+                }
+            }
+
+            frontier = next;
+        }
+
+        labels
+            .into_iter()
+            .map(|l| match l {
+                Label::Closest(i) => Some(i),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // The closest location to every cell, searching outward from the cell's column through
+    // the locations sorted by x. A location whose horizontal gap alone is further than the
+    // closest found so far can't be closer, nor can any beyond it, so each search stops
+    // there. Locations at exactly that distance are still checked, keeping ties exact.
+    //
+    fn sweep_labels(&self) -> Vec<Option<usize>> {
+        let mut by_x: Vec<usize> = (0..self.locations.len()).collect();
+        by_x.sort_by_key(|&i| self.locations[i].x);
+        let xs: Vec<isize> = by_x.iter().map(|&i| self.locations[i].x).collect();
+
+        let mut labels = Vec::with_capacity(self.width() * self.height());
+        for y in self.bounds.min_y..=self.bounds.max_y {
+            for x in self.bounds.min_x..=self.bounds.max_x {
+                let cell = Coordinate { x, y };
+                let start = xs.partition_point(|&lx| lx < x);
+                let (mut closest, mut min) = (None, usize::MAX);
+
+                // Returns false once the search has gone far enough in one direction
+                let mut visit = |j: usize| {
+                    let dx = (xs[j] - x).unsigned_abs();
+                    if dx * dx > min {
+                        return false;
+                    }
+                    let dist = self.metric.distance(&self.locations[by_x[j]], &cell);
+                    if dist < min {
+                        min = dist;
+                        closest = Some(by_x[j]);
+                    } else if dist == min {
+                        closest = None;
+                    }
+                    true
+                };
+                (start..xs.len()).all(&mut visit);
+                (0..start).rev().all(&mut visit);
+
+                labels.push(closest);
+            }
+        }
+
+        labels
+    }
+
+    // The area of each location's region, in the same order as the locations
+    //
+    pub fn areas(&self) -> Vec<Area> {
+        self.areas_from(&self.labels())
+    }
+
+    fn areas_from(&self, labels: &[Option<usize>]) -> Vec<Area> {
+        let (w, h) = (self.width(), self.height());
+        let mut areas = vec![Area::Finite(0); self.locations.len()];
+
+        for (idx, &label) in labels.iter().enumerate() {
+            if let Some(i) = label {
+                let (x, y) = (idx % w, idx / w);
+                let on_edge = x == 0 || x == w - 1 || y == 0 || y == h - 1;
+                areas[i] = match areas[i] {
+                    _ if on_edge => Area::Infinite,
+                    Area::Finite(n) => Area::Finite(n + 1),
+                    Area::Infinite => Area::Infinite,
+                };
+            }
+        }
+
+        areas
+    }
+
+    // Write the grid as a binary PPM image, one pixel per cell. Each region takes the
+    // colour of its location, infinite regions are darkened, ties are black and the
+    // locations themselves are white. Cells in `safe` are lightened, and the image grows
+    // to include them if the region extends beyond the bounding box.
+    //
+    pub fn render<W: Write>(&self, out: &mut W, safe: Option<&SafeRegion>) -> io::Result<()> {
+        let mut b = self.bounds;
+        if let Some(sb) = safe.and_then(|s| s.bounds) {
+            b = Bounds {
+                min_x: b.min_x.min(sb.min_x),
+                max_x: b.max_x.max(sb.max_x),
+                min_y: b.min_y.min(sb.min_y),
+                max_y: b.max_y.max(sb.max_y),
+            };
+        }
+
+        let labels = self.labels();
+        let areas = self.areas_from(&labels);
+        let safe_cells: HashSet<&Coordinate> =
+            safe.map_or(HashSet::new(), |s| s.cells.iter().collect());
+
+        write!(
+            out,
+            "P6\n{} {}\n255\n",
+            b.max_x - b.min_x + 1,
+            b.max_y - b.min_y + 1
+        )?;
+        for y in b.min_y..=b.max_y {
+            for x in b.min_x..=b.max_x {
+                let cell = Coordinate { x, y };
+                let closest = match self.index(&cell) {
+                    Some(idx) => labels[idx],
+                    None => self.closest(&cell),
+                };
+                let mut colour = match closest {
+                    None => TIE,
+                    Some(i) if self.locations[i] == cell => LOCATION,
+                    Some(i) if areas[i] == Area::Infinite => blend(palette(i), TIE, 0.6),
+                    Some(i) => palette(i),
+                };
+                if safe_cells.contains(&cell) {
+                    colour = blend(colour, LOCATION, 0.5);
+                }
+                out.write_all(&colour)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    //Find all the locations with a finite region
+    //
+    pub fn finite(&self) -> Vec<Coordinate> {
+        self.locations
+            .iter()
+            .zip(self.areas())
+            .filter(|(_, area)| *area != Area::Infinite)
+            .map(|(coord, _)| coord.clone())
+            .collect()
+    }
+}
+
+type Rgb = [u8; 3];
+
+const TIE: Rgb = [0, 0, 0];
+const LOCATION: Rgb = [255, 255, 255];
+
+// A distinct colour for each location, spacing out hues by the golden angle
+//
+fn palette(i: usize) -> Rgb {
+    let h = (i as f64 * 137.508) % 360.0;
+    let (s, v) = (0.65, 0.95);
+
+    let c = v * s;
+    let x = c * (1.0 - ((h / 60.0) % 2.0 - 1.0).abs());
+    let (r, g, b) = match (h / 60.0) as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = v - c;
+
+    [r, g, b].map(|channel| ((channel + m) * 255.0).round() as u8)
+}
+
+fn blend(from: Rgb, to: Rgb, t: f64) -> Rgb {
+    let mut mixed = from;
+    for (m, &target) in mixed.iter_mut().zip(to.iter()) {
+        *m = (*m as f64 + (target as f64 - *m as f64) * t).round() as u8;
+    }
+    mixed
+}
+
+// The cells whose total Manhattan distance to all locations is less than some threshold
+//
+#[derive(Debug, PartialEq, Eq)]
+pub struct SafeRegion {
+    pub area: usize,
+    pub bounds: Option<Bounds>,
+    pub cells: Vec<Coordinate>,
+}
+
+// The total distance along one axis from each position in `range` to every location
+//
+fn axis_totals(positions: &[isize], range: &RangeInclusive<isize>) -> Vec<usize> {
+    range
+        .clone()
+        .map(|p| positions.iter().map(|q| (p - q).unsigned_abs()).sum())
+        .collect()
+}
+
+pub fn safe_region(locations: &[Coordinate], threshold: usize) -> SafeRegion {
+    let grid = Grid::new(locations.to_vec(), Metric::Manhattan);
+
+    // Every step taken away from the bounding box moves a cell one further from every
+    // location, so nothing more than threshold / n steps outside it can be safe
+    let margin = (threshold / locations.len()) as isize;
+    let xs = grid.bounds.min_x - margin..=grid.bounds.max_x + margin;
+    let ys = grid.bounds.min_y - margin..=grid.bounds.max_y + margin;
+
+    // Manhattan distance separates into its x and y components, so the totals along each
+    // axis only need to be computed once
+    let x_totals = axis_totals(&locations.iter().map(|c| c.x).collect::<Vec<_>>(), &xs);
+    let y_totals = axis_totals(&locations.iter().map(|c| c.y).collect::<Vec<_>>(), &ys);
+
+    let mut cells = Vec::new();
+    for (x, x_total) in xs.zip(x_totals) {
+        for (y, y_total) in ys.clone().zip(y_totals.iter()) {
+            if x_total + y_total < threshold {
+                cells.push(Coordinate { x, y });
+            }
+        }
+    }
+
+    let bounds = if cells.is_empty() {
+        None
+    } else {
+        Some(Bounds {
+            min_x: cells.iter().map(|c| c.x).min().unwrap(),
+            max_x: cells.iter().map(|c| c.x).max().unwrap(),
+            min_y: cells.iter().map(|c| c.y).min().unwrap(),
+            max_y: cells.iter().map(|c| c.y).max().unwrap(),
+        })
+    };
+
+    SafeRegion {
+        area: cells.len(),
+        bounds,
+        cells,
+    }
 }
 
 #[aoc_generator(day6)]
@@ -62,7 +441,7 @@ pub fn input_coordiantes(input: &str) -> Vec<Coordinate> {
     input
         .lines()
         .map(|l| {
-            let coords: Vec<usize> = l.split(',').map(|c| c.trim().parse().unwrap()).collect();
+            let coords: Vec<isize> = l.split(',').map(|c| c.trim().parse().unwrap()).collect();
             Coordinate {
                 x: coords[0],
                 y: coords[1],
@@ -73,64 +452,20 @@ pub fn input_coordiantes(input: &str) -> Vec<Coordinate> {
 
 #[aoc(day6, part1)]
 pub fn part1(input: &[Coordinate]) -> usize {
-    let grid = Grid::new(input.to_vec());
-    let mut closest_locations: Vec<Coordinate> = Vec::new();
-
-    for x in grid.bounds.min_x..grid.bounds.max_x + 1 {
-        for y in grid.bounds.min_y..grid.bounds.max_y + 1 {
-            //At each bounding point, calculate the closest location and determine if it is
-            //indeed the closest (not equidistant to another location)
-            let current = Coordinate { x, y };
-            let closest = grid
-                .locations
-                .iter()
-                .min_by_key(|o| o.manhattan_dist(&current))
-                .unwrap();
-            let dist = closest.manhattan_dist(&current);
-
-            if grid
-                .locations
-                .iter()
-                .filter(|o| o.manhattan_dist(&current) == dist)
-                .count()
-                == 1
-            {
-                closest_locations.push(closest.clone());
-            }
-        }
-    }
-
-    let mut max = 0;
-    for f in grid.finite.iter() {
-        let area = closest_locations.iter().filter(|o| o == &f).count();
-        if area > max {
-            max = area;
-        }
-    }
-
-    max
+    Grid::new(input.to_vec(), Metric::Manhattan)
+        .areas()
+        .into_iter()
+        .filter_map(|area| match area {
+            Area::Finite(n) => Some(n),
+            Area::Infinite => None,
+        })
+        .max()
+        .unwrap_or(0)
 }
 
 #[aoc(day6, part2)]
 pub fn part2(input: &[Coordinate]) -> usize {
-    let grid = Grid::new(input.to_vec());
-
-    let mut cnt = 0;
-    for x in grid.bounds.min_x..grid.bounds.max_x + 1 {
-        for y in grid.bounds.min_y..grid.bounds.max_y + 1 {
-            let current = Coordinate { x, y };
-            let sum: usize = grid
-                .locations
-                .iter()
-                .map(|o| o.manhattan_dist(&current))
-                .sum();
-            if sum < 10000 {
-                cnt += 1;
-            }
-        }
-    }
-
-    cnt
+    safe_region(input, 10000).area
 }
 
 #[cfg(test)]
@@ -176,13 +511,179 @@ mod tests {
             max_y: 9,
         };
 
-        let grid = Grid::new(input_coordiantes(TEST_STR));
-        assert_eq!(grid.finite, finite);
+        let grid = Grid::new(input_coordiantes(TEST_STR), Metric::Manhattan);
+        assert_eq!(grid.finite(), finite);
         assert_eq!(grid.bounds, bounds);
     }
 
+    #[test]
+    fn metrics() {
+        let a = Coordinate { x: 6, y: 2 };
+        let b = Coordinate { x: 0, y: 10 };
+
+        assert_eq!(Metric::Manhattan.distance(&a, &b), 14);
+        assert_eq!(Metric::Chebyshev.distance(&a, &b), 8);
+        assert_eq!(Metric::Euclidean.distance(&a, &b), 100);
+    }
+
+    #[test]
+    fn areas() {
+        use self::Area::*;
+        let areas = |metric| Grid::new(input_coordiantes(TEST_STR), metric).areas();
+
+        assert_eq!(
+            areas(Metric::Manhattan),
+            vec![
+                Infinite,
+                Infinite,
+                Infinite,
+                Finite(9),
+                Finite(17),
+                Infinite
+            ]
+        );
+        assert_eq!(
+            areas(Metric::Chebyshev),
+            vec![
+                Infinite,
+                Infinite,
+                Infinite,
+                Finite(8),
+                Finite(10),
+                Infinite
+            ]
+        );
+        assert_eq!(
+            areas(Metric::Euclidean),
+            vec![
+                Infinite,
+                Infinite,
+                Infinite,
+                Finite(10),
+                Finite(16),
+                Infinite
+            ]
+        );
+    }
+
+    // A deterministic scattering of coordinates, some of them duplicated
+    fn scattered(n: usize, size: isize) -> Vec<Coordinate> {
+        let mut seed: u64 = 0x2018_0006;
+        (0..n)
+            .map(|_| {
+                seed = seed
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                Coordinate {
+                    x: (seed >> 33) as isize % size,
+                    y: (seed >> 17) as isize % size,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn labels_match_brute_force() {
+        for &metric in &[Metric::Manhattan, Metric::Chebyshev, Metric::Euclidean] {
+            for &(n, size) in &[(6, 10), (50, 40), (300, 60)] {
+                let grid = Grid::new(scattered(n, size), metric);
+                let expected: Vec<Option<usize>> = (grid.bounds.min_y..=grid.bounds.max_y)
+                    .flat_map(|y| (grid.bounds.min_x..=grid.bounds.max_x).map(move |x| (x, y)))
+                    .map(|(x, y)| grid.closest(&Coordinate { x, y }))
+                    .collect();
+
+                assert_eq!(grid.labels(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn flood_at_scale() {
+        let grid = Grid::new(scattered(20_000, 1_000), Metric::Manhattan);
+        let labels = grid.labels();
+
+        assert_eq!(labels.len(), grid.width() * grid.height());
+        assert!(labels.iter().filter(|l| l.is_some()).count() > labels.len() / 2);
+        assert!(grid.areas().contains(&Area::Infinite));
+    }
+
+    #[test]
+    fn interior_region_reaching_edge() {
+        // (5, 1) lies strictly inside the bounding box, but owns the edge cell (5, 0)
+        let grid = Grid::new(
+            input_coordiantes("0, 0\n10, 0\n0, 10\n10, 10\n5, 1\n5, 6"),
+            Metric::Manhattan,
+        );
+
+        assert_eq!(grid.areas()[4], Area::Infinite);
+        assert_eq!(grid.finite(), vec![]);
+    }
+
     #[test]
     fn sample1() {
         assert_eq!(part1(&input_coordiantes(TEST_STR)), 17);
     }
+
+    #[test]
+    fn render() {
+        let header = b"P6\n8 9\n255\n";
+        let pixel = |image: &[u8], x: isize, y: isize| -> Rgb {
+            let i = header.len() + 3 * ((y - 1) * 8 + (x - 1)) as usize;
+            [image[i], image[i + 1], image[i + 2]]
+        };
+        let input = input_coordiantes(TEST_STR);
+        let grid = Grid::new(input.clone(), Metric::Manhattan);
+
+        let mut image = Vec::new();
+        grid.render(&mut image, None).unwrap();
+        assert_eq!(&image[..header.len()], &header[..]);
+        assert_eq!(image.len(), header.len() + 8 * 9 * 3);
+        assert_eq!(pixel(&image, 1, 1), LOCATION);
+        assert_eq!(pixel(&image, 5, 1), TIE);
+        assert_eq!(pixel(&image, 4, 4), palette(3));
+        assert_eq!(pixel(&image, 2, 1), blend(palette(0), TIE, 0.6));
+        assert_eq!(pixel(&image, 4, 3), palette(3));
+
+        let mut overlaid = Vec::new();
+        grid.render(&mut overlaid, Some(&safe_region(&input, 32)))
+            .unwrap();
+        assert_eq!(pixel(&overlaid, 4, 3), blend(palette(3), LOCATION, 0.5));
+        assert_eq!(pixel(&overlaid, 1, 1), LOCATION);
+    }
+
+    #[test]
+    fn sample2() {
+        let region = safe_region(&input_coordiantes(TEST_STR), 32);
+
+        assert_eq!(region.area, 16);
+        assert!(region.cells.contains(&Coordinate { x: 4, y: 3 }));
+        assert_eq!(
+            region.bounds,
+            Some(Bounds {
+                min_x: 2,
+                max_x: 6,
+                min_y: 3,
+                max_y: 6,
+            })
+        );
+    }
+
+    #[test]
+    fn safe_region_beyond_bounds() {
+        let region = safe_region(&input_coordiantes("0, 0\n2, 0"), 6);
+
+        // Total distance is 2 anywhere on the segment, growing by 2 with every step away
+        assert_eq!(region.area, 11);
+        assert_eq!(
+            region.bounds,
+            Some(Bounds {
+                min_x: -1,
+                max_x: 3,
+                min_y: -1,
+                max_y: 1,
+            })
+        );
+        assert!(region.cells.contains(&Coordinate { x: -1, y: 0 }));
+        assert_eq!(safe_region(&input_coordiantes("0, 0\n2, 0"), 2).area, 0);
+    }
 }
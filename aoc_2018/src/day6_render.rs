@@ -0,0 +1,30 @@
+// Day 6: Chronal Coordinates (region rendering)
+//
+// Renders the regions closest to each coordinate, overlaid with the safe region, as a PPM
+// image. Run with: `cargo run --bin day6_render [output file] [safe distance threshold]`
+//
+extern crate aoc_2018;
+
+use aoc_2018::day6::{input_coordiantes, safe_region, Grid, Metric};
+use std::env;
+use std::fs::{self, File};
+use std::io::BufWriter;
+
+fn main() {
+    let path = "input/2018/day6.txt";
+    let contents = match fs::read_to_string(path) {
+        Err(e) => panic!("Couldn't open {}: {}", path, e),
+        Ok(contents) => contents,
+    };
+
+    let output = env::args().nth(1).unwrap_or_else(|| "day6.ppm".to_string());
+    let threshold: usize = env::args().nth(2).map_or(10000, |t| t.parse().unwrap());
+
+    let locations = input_coordiantes(contents.trim());
+    let region = safe_region(&locations, threshold);
+    let grid = Grid::new(locations, Metric::Manhattan);
+
+    let mut file = BufWriter::new(File::create(&output).unwrap());
+    grid.render(&mut file, Some(&region)).unwrap();
+    println!("Wrote {}", output);
+}
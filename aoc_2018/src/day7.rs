@@ -1,13 +1,18 @@
 //Day 7: The Sum of Its Parts
 //
 use regex::Regex;
-use std::collections::{BTreeMap, BTreeSet, BinaryHeap};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt;
 
-pub fn input_steps(input: &str) -> BTreeMap<&str, BTreeSet<&str>> {
-    let re =
-        Regex::new(r"^Step (.{1}?) must be finished before step (.{1}?) can begin\.$").unwrap();
+// Each step, and the steps that must be finished before it can begin
+pub type Steps<'a> = BTreeMap<&'a str, BTreeSet<&'a str>>;
 
-    let mut instructions: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+pub fn input_steps(input: &str) -> Steps<'_> {
+    let re = Regex::new(r"^Step (\S+) must be finished before step (\S+) can begin\.$").unwrap();
+
+    let mut instructions: Steps = BTreeMap::new();
 
     for l in input.lines() {
         let caps: Vec<&str> = re
@@ -17,94 +22,403 @@ pub fn input_steps(input: &str) -> BTreeMap<&str, BTreeSet<&str>> {
             .map(|c| c.unwrap().as_str())
             .collect();
 
-        instructions
-            .entry(caps[2])
-            .or_insert_with(BTreeSet::new)
-            .insert(caps[1]);
+        instructions.entry(caps[2]).or_default().insert(caps[1]);
 
-        instructions.entry(caps[1]).or_insert_with(BTreeSet::new);
+        instructions.entry(caps[1]).or_default();
     }
 
     instructions
 }
 
-pub fn complete(instruction: &str, instructions: &mut BTreeMap<&str, BTreeSet<&str>>) -> String {
-    if instructions.is_empty() {
-        return String::new();
-    } // We've completely processed the entire instuction set
+// The steps that could never begin, because they (transitively) depend on themselves.
+// Steps that are only held up by a cycle, without being part of one, are left out.
+//
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cycle {
+    pub steps: Vec<String>,
+}
 
-    // Complete the chosen instruction by removing it's entry, and wiping it from the instruction
-    // set of other entries
-    instructions.remove(instruction);
-    for steps in instructions.values_mut() {
-        steps.remove(instruction);
+impl fmt::Display for Cycle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "dependency cycle between steps {}",
+            self.steps.join(", ")
+        )
     }
+}
 
-    let mut order = String::new(); // The order in which instructions were completed
-    let free: Vec<&str> = instructions
-        .iter()
-        .filter(|(_, v)| v.is_empty())
-        .map(|(&k, _)| k)
-        .collect();
-    free.iter().for_each(|k| {
-        // Find all the instructions that can now be completed
-        order.push_str(&complete(k, instructions)); //It's recursive baby!
-    });
+impl Error for Cycle {}
+
+impl Cycle {
+    // The cycles among the steps left waiting, found as the strongly connected components of
+    // their prerequisites (with Tarjan's algorithm)
+    //
+    fn among(waiting: &Steps) -> Cycle {
+        let mut search = Components {
+            graph: waiting,
+            index: BTreeMap::new(),
+            low: BTreeMap::new(),
+            stack: Vec::new(),
+            on_stack: BTreeSet::new(),
+            cyclic: BTreeSet::new(),
+        };
+        for &step in waiting.keys() {
+            if !search.index.contains_key(step) {
+                search.visit(step);
+            }
+        }
 
-    order.push_str(instruction);
-    order
+        Cycle {
+            steps: search.cyclic.into_iter().map(|s| s.to_string()).collect(),
+        }
+    }
 }
 
-#[aoc(day7, part1)]
-pub fn part1(input: &str) -> String {
-    let mut instructions = input_steps(input);
+struct Components<'s, 'a> {
+    graph: &'s Steps<'a>,
+    index: BTreeMap<&'a str, usize>, // The order each step was first visited in
+    low: BTreeMap<&'a str, usize>,   // The earliest visited step on the stack each one reaches
+    stack: Vec<&'a str>,             // Visited steps not yet assigned a component
+    on_stack: BTreeSet<&'a str>,     // The same steps, for quick lookup
+    cyclic: BTreeSet<&'a str>,       // Steps in a component that forms a cycle
+}
+
+impl<'s, 'a> Components<'s, 'a> {
+    fn visit(&mut self, step: &'a str) {
+        let order = self.index.len();
+        self.index.insert(step, order);
+        self.low.insert(step, order);
+        self.stack.push(step);
+        self.on_stack.insert(step);
+
+        let graph = self.graph;
+        for &prerequisite in graph.get(step).into_iter().flatten() {
+            let reached = if !self.index.contains_key(prerequisite) {
+                self.visit(prerequisite);
+                self.low[prerequisite]
+            } else if self.on_stack.contains(prerequisite) {
+                self.index[prerequisite]
+            } else {
+                continue;
+            };
+            if reached < self.low[step] {
+                self.low.insert(step, reached);
+            }
+        }
+
+        // The step was the first of its component visited, so the component is every step
+        // above it on the stack
+        if self.low[step] == order {
+            let at = self.stack.iter().rposition(|&s| s == step).unwrap();
+            let component = self.stack.split_off(at);
+            for s in &component {
+                self.on_stack.remove(s);
+            }
 
-    let start: &str;
-    {
-        // Find the first alphabetical available step
-        start = instructions.iter().find(|(_, v)| v.is_empty()).unwrap().0;
+            let loops = graph.get(step).is_some_and(|p| p.contains(step));
+            if component.len() > 1 || loops {
+                self.cyclic.extend(component);
+            }
+        }
     }
+}
+
+// A step worked on by a worker (numbered from 0) from `start` until just before `end`
+//
+#[derive(Debug, Clone, PartialEq)]
+pub struct Task<'a> {
+    pub worker: usize,
+    pub step: &'a str,
+    pub start: usize,
+    pub end: usize,
+}
 
-    complete(start, &mut instructions).chars().rev().collect()
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule<'a> {
+    pub workers: usize,
+    pub timeline: Vec<Task<'a>>, // Every step, in the order they were started
+    pub order: Vec<&'a str>,     // The order in which the steps were completed
+    pub makespan: usize,         // The time at which the last step completed
 }
 
-#[aoc(day7, part2)]
-pub fn part2(input: &str) -> isize {
-    //Constants defined in aoc question
-    let num_workers = 5;
-    let value_offset = 60;
-
-    let mut time = 0;
-    let mut workers: BinaryHeap<(isize, &str)> = BinaryHeap::new(); // (completion_time, instruction)
-    let mut instructions = input_steps(input);
-
-    while !(instructions.is_empty() && workers.is_empty()) {
-        while workers.len() < num_workers && !instructions.is_empty() {
-            let next: &str;
-            {
-                if let Some(available_task) = instructions.iter().find(|(_, v)| v.is_empty()) {
-                    next = available_task.0; // Get next instruction to complete (alphabetical order)
-                } else {
-                    break;
+impl<'a> Schedule<'a> {
+    // A second-by-second table of what each worker is doing, and the steps done so far,
+    // laid out like the one in the puzzle description
+    //
+    pub fn gantt(&self) -> String {
+        let width = self
+            .timeline
+            .iter()
+            .map(|t| t.step.len())
+            .chain((1..=self.workers).map(|w| format!("Worker {}", w).len()))
+            .max()
+            .unwrap();
+        let separator = if self.order.iter().all(|s| s.len() == 1) {
+            ""
+        } else {
+            ","
+        };
+
+        let mut chart = String::from("Second");
+        for w in 1..=self.workers {
+            chart.push_str(&format!(
+                "   {:^width$}",
+                format!("Worker {}", w),
+                width = width
+            ));
+        }
+        chart.push_str("   Done\n");
+
+        for second in 0..=self.makespan {
+            let mut row = format!("{:>4}  ", second);
+            for w in 0..self.workers {
+                let step = self
+                    .timeline
+                    .iter()
+                    .find(|t| t.worker == w && t.start <= second && second < t.end)
+                    .map_or(".", |t| t.step);
+                row.push_str(&format!("   {:^width$}", step, width = width));
+            }
+
+            let done: Vec<&str> = self
+                .order
+                .iter()
+                .filter(|&&s| self.timeline.iter().any(|t| t.step == s && t.end <= second))
+                .cloned()
+                .collect();
+            row.push_str(&format!("   {}", done.join(separator)));
+
+            chart.push_str(row.trim_end());
+            chart.push('\n');
+        }
+
+        chart
+    }
+
+    // The timeline as CSV, numbering the workers from 1 as the chart does
+    //
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("worker,step,start,end\n");
+        for t in &self.timeline {
+            csv.push_str(&format!(
+                "{},{},{},{}\n",
+                t.worker + 1,
+                t.step,
+                t.start,
+                t.end
+            ));
+        }
+        csv
+    }
+}
+
+// Assigns available steps to idle workers until every step is complete. Each step takes
+// `duration(step)` seconds, and when more steps are available than there are idle workers
+// they are started in the order given by `order`.
+//
+pub struct Scheduler<D, O> {
+    workers: usize,
+    duration: D,
+    order: O,
+}
+
+impl<D, O> Scheduler<D, O>
+where
+    D: Fn(&str) -> usize,
+    O: Fn(&str, &str) -> Ordering,
+{
+    pub fn new(workers: usize, duration: D, order: O) -> Scheduler<D, O> {
+        assert!(workers > 0, "at least one worker is required");
+        Scheduler {
+            workers,
+            duration,
+            order,
+        }
+    }
+
+    pub fn run<'a>(&self, steps: &Steps<'a>) -> Result<Schedule<'a>, Cycle> {
+        let mut waiting = steps.clone();
+        // What each worker is busy with, as (completion_time, step)
+        let mut working: Vec<Option<(usize, &str)>> = vec![None; self.workers];
+        let mut timeline = Vec::new();
+        let mut order = Vec::new();
+        let mut time = 0;
+
+        loop {
+            let mut available: Vec<&str> = waiting
+                .iter()
+                .filter(|(_, v)| v.is_empty())
+                .map(|(&k, _)| k)
+                .collect();
+            available.sort_by(|a, b| (self.order)(a, b));
+
+            // Idle workers pick up the available steps, lowest numbered worker first
+            let mut available = available.into_iter();
+            for (worker, slot) in working.iter_mut().enumerate() {
+                if slot.is_some() {
+                    continue;
+                }
+                if let Some(step) = available.next() {
+                    let end = time + (self.duration)(step);
+                    waiting.remove(step);
+                    *slot = Some((end, step));
+                    timeline.push(Task {
+                        worker,
+                        step,
+                        start: time,
+                        end,
+                    });
+                }
+            }
+
+            // Skip ahead to whenever the next step(s) complete
+            time = match working.iter().flatten().map(|&(t, _)| t).min() {
+                Some(t) => t,
+                None => break,
+            };
+
+            let mut completed = Vec::new();
+            for slot in working.iter_mut() {
+                if let Some((t, step)) = *slot {
+                    if t == time {
+                        completed.push(step);
+                        *slot = None;
+                    }
                 }
             }
+            completed.sort_by(|a, b| (self.order)(a, b));
 
-            instructions.remove(next);
-            let value = next.chars().next().unwrap() as isize - isize::from(b'A') + 1;
-            let completion_time = time - value_offset - value;
-            workers.push((completion_time, next));
+            for step in completed {
+                for prerequisites in waiting.values_mut() {
+                    prerequisites.remove(step);
+                }
+                order.push(step);
+            }
         }
 
-        //The next instruction has completed, remove all instances from the instruction set
-        let (t, next) = workers.pop().unwrap();
-        for steps in instructions.values_mut() {
-            steps.remove(next);
+        if !waiting.is_empty() {
+            return Err(Cycle::among(&waiting));
         }
 
-        time = t; // Update the time to whatever the worker completed
+        Ok(Schedule {
+            workers: self.workers,
+            timeline,
+            order,
+            makespan: time,
+        })
     }
+}
 
-    -time
+// When a step can run if there are as many workers as needed. Starting a step any later
+// than `latest_start` delays the completion of every step.
+//
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepTiming {
+    pub duration: usize,
+    pub earliest_start: usize,
+    pub latest_start: usize,
+}
+
+impl StepTiming {
+    pub fn slack(&self) -> usize {
+        self.latest_start - self.earliest_start
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CriticalPath<'a> {
+    pub timings: BTreeMap<&'a str, StepTiming>,
+    pub path: Vec<&'a str>, // A chain of steps without slack, running from start to finish
+    pub makespan: usize,    // The shortest possible time to complete every step
+}
+
+pub fn critical_path<'a, D>(steps: &Steps<'a>, duration: D) -> Result<CriticalPath<'a>, Cycle>
+where
+    D: Fn(&str) -> usize,
+{
+    // With a worker for every step, each one starts as soon as its prerequisites are done
+    let schedule = Scheduler::new(steps.len().max(1), &duration, |a, b| a.cmp(b)).run(steps)?;
+
+    let mut dependents: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for (&step, prerequisites) in steps {
+        for &p in prerequisites {
+            dependents.entry(p).or_default().push(step);
+        }
+    }
+
+    // Work backwards from the end: a step must finish before any of its dependents have to
+    // start, and steps nothing depends on may finish as late as the makespan
+    let mut timings: BTreeMap<&str, StepTiming> = BTreeMap::new();
+    for &step in schedule.order.iter().rev() {
+        let task = schedule.timeline.iter().find(|t| t.step == step).unwrap();
+        let latest_finish = dependents
+            .get(step)
+            .into_iter()
+            .flatten()
+            .map(|d| timings[d].latest_start)
+            .min()
+            .unwrap_or(schedule.makespan);
+
+        timings.insert(
+            step,
+            StepTiming {
+                duration: task.end - task.start,
+                earliest_start: task.start,
+                latest_start: latest_finish - (task.end - task.start),
+            },
+        );
+    }
+
+    let critical = |s: &str| timings[s].slack() == 0;
+    let mut path: Vec<&str> = steps
+        .iter()
+        .filter(|(_, p)| p.is_empty())
+        .map(|(&s, _)| s)
+        .find(|s| critical(s))
+        .into_iter()
+        .collect();
+    while let Some(&last) = path.last() {
+        let end = timings[last].earliest_start + timings[last].duration;
+        match dependents
+            .get(last)
+            .into_iter()
+            .flatten()
+            .filter(|&d| timings[d].earliest_start == end)
+            .find(|s| critical(s))
+        {
+            Some(&next) => path.push(next),
+            None => break,
+        }
+    }
+
+    Ok(CriticalPath {
+        timings,
+        path,
+        makespan: schedule.makespan,
+    })
+}
+
+// Step 'A' takes one second, 'B' two seconds, and so on
+//
+fn step_value(step: &str) -> usize {
+    (step.bytes().next().unwrap() - b'A') as usize + 1
+}
+
+#[aoc(day7, part1)]
+pub fn part1(input: &str) -> String {
+    // With a single worker, steps complete in exactly the order they become available
+    let scheduler = Scheduler::new(1, |_| 1, |a, b| a.cmp(b));
+
+    scheduler.run(&input_steps(input)).unwrap().order.concat()
+}
+
+#[aoc(day7, part2)]
+pub fn part2(input: &str) -> usize {
+    //Constants defined in aoc question
+    let scheduler = Scheduler::new(5, |s| 60 + step_value(s), |a, b| a.cmp(b));
+
+    scheduler.run(&input_steps(input)).unwrap().makespan
 }
 
 #[cfg(test)]
@@ -121,6 +435,177 @@ mod tests {
 
     #[test]
     fn sample1() {
-        assert_eq!(part1(&TEST_STR), "CABDFE");
+        assert_eq!(part1(TEST_STR), "CABDFE");
+    }
+
+    #[test]
+    fn sample2() {
+        let scheduler = Scheduler::new(2, step_value, |a, b| a.cmp(b));
+        let schedule = scheduler.run(&input_steps(TEST_STR)).unwrap();
+
+        assert_eq!(schedule.makespan, 15);
+        assert_eq!(schedule.order, vec!["C", "A", "B", "F", "D", "E"]);
+    }
+
+    #[test]
+    fn timeline() {
+        let scheduler = Scheduler::new(2, step_value, |a, b| a.cmp(b));
+        let schedule = scheduler.run(&input_steps(TEST_STR)).unwrap();
+
+        assert_eq!(
+            schedule.timeline[..3],
+            [
+                Task {
+                    worker: 0,
+                    step: "C",
+                    start: 0,
+                    end: 3
+                },
+                Task {
+                    worker: 0,
+                    step: "A",
+                    start: 3,
+                    end: 4
+                },
+                Task {
+                    worker: 1,
+                    step: "F",
+                    start: 3,
+                    end: 9
+                },
+            ]
+        );
+        assert_eq!(
+            schedule.to_csv(),
+            "worker,step,start,end\n\
+             1,C,0,3\n\
+             1,A,3,4\n\
+             2,F,3,9\n\
+             1,B,4,6\n\
+             1,D,6,10\n\
+             1,E,10,15\n"
+        );
+    }
+
+    #[test]
+    fn gantt() {
+        let scheduler = Scheduler::new(2, step_value, |a, b| a.cmp(b));
+        let schedule = scheduler.run(&input_steps(TEST_STR)).unwrap();
+        let expected = "Second   Worker 1   Worker 2   Done\n\
+                        \x20  0        C          .\n\
+                        \x20  1        C          .\n\
+                        \x20  2        C          .\n\
+                        \x20  3        A          F       C\n\
+                        \x20  4        B          F       CA\n\
+                        \x20  5        B          F       CA\n\
+                        \x20  6        D          F       CAB\n\
+                        \x20  7        D          F       CAB\n\
+                        \x20  8        D          F       CAB\n\
+                        \x20  9        D          .       CABF\n\
+                        \x20 10        E          .       CABFD\n\
+                        \x20 11        E          .       CABFD\n\
+                        \x20 12        E          .       CABFD\n\
+                        \x20 13        E          .       CABFD\n\
+                        \x20 14        E          .       CABFD\n\
+                        \x20 15        .          .       CABFDE\n";
+
+        assert_eq!(schedule.gantt(), expected);
+    }
+
+    #[test]
+    fn named_steps() {
+        let steps = input_steps(
+            "Step fetch must be finished before step build can begin.\n\
+             Step configure must be finished before step build can begin.\n\
+             Step build must be finished before step test can begin.\n\
+             Step build must be finished before step package can begin.",
+        );
+        let durations = |s: &str| s.len();
+
+        // Longest step first
+        let scheduler = Scheduler::new(2, durations, |a, b| b.len().cmp(&a.len()));
+        let schedule = scheduler.run(&steps).unwrap();
+        assert_eq!(
+            schedule.order,
+            vec!["fetch", "configure", "build", "test", "package"]
+        );
+        assert_eq!(schedule.makespan, 9 + 5 + 7);
+
+        let scheduler = Scheduler::new(1, durations, |a, b| a.cmp(b));
+        let schedule = scheduler.run(&steps).unwrap();
+        assert_eq!(schedule.makespan, 9 + 5 + 5 + 7 + 4);
+    }
+
+    #[test]
+    fn critical() {
+        let analysis = critical_path(&input_steps(TEST_STR), step_value).unwrap();
+        let slack: Vec<(&str, usize)> = analysis
+            .timings
+            .iter()
+            .map(|(&s, t)| (s, t.slack()))
+            .collect();
+
+        assert_eq!(analysis.makespan, 14);
+        assert_eq!(analysis.path, vec!["C", "F", "E"]);
+        assert_eq!(
+            slack,
+            vec![("A", 1), ("B", 3), ("C", 0), ("D", 1), ("E", 0), ("F", 0)]
+        );
+        assert_eq!(
+            analysis.timings["D"],
+            StepTiming {
+                duration: 4,
+                earliest_start: 4,
+                latest_start: 5
+            }
+        );
+        assert!(critical_path(&BTreeMap::new(), step_value)
+            .unwrap()
+            .path
+            .is_empty());
+    }
+
+    #[test]
+    fn cycle() {
+        let steps = input_steps(
+            "Step A must be finished before step B can begin.\n\
+             Step B must be finished before step C can begin.\n\
+             Step C must be finished before step B can begin.\n\
+             Step C must be finished before step D can begin.",
+        );
+        let scheduler = Scheduler::new(2, |_| 1, |a, b| a.cmp(b));
+
+        assert!(critical_path(&steps, |_| 1).is_err());
+        assert_eq!(
+            scheduler.run(&steps),
+            Err(Cycle {
+                steps: vec!["B".to_string(), "C".to_string()]
+            })
+        );
+    }
+
+    #[test]
+    fn cycle_members() {
+        // E sits between two cycles, and F only waits on the second, so neither is part of one
+        let steps = input_steps(
+            "Step A must be finished before step B can begin.\n\
+             Step B must be finished before step A can begin.\n\
+             Step B must be finished before step E can begin.\n\
+             Step E must be finished before step C can begin.\n\
+             Step C must be finished before step D can begin.\n\
+             Step D must be finished before step C can begin.\n\
+             Step D must be finished before step F can begin.\n\
+             Step G must be finished before step G can begin.\n\
+             Step H must be finished before step I can begin.",
+        );
+        let error = Scheduler::new(1, |_| 1, |a, b| a.cmp(b))
+            .run(&steps)
+            .unwrap_err();
+
+        assert_eq!(error.steps, vec!["A", "B", "C", "D", "G"]);
+        assert_eq!(
+            error.to_string(),
+            "dependency cycle between steps A, B, C, D, G"
+        );
     }
 }
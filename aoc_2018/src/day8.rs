@@ -1,5 +1,9 @@
 // Day 8: Memory Maneuver
 //
+use std::error::Error;
+use std::fmt;
+use std::mem;
+
 #[derive(Default, Debug, PartialEq)]
 pub struct Node {
     children: Vec<Node>,
@@ -9,61 +13,289 @@ pub struct Node {
 
 impl std::convert::AsRef<Node> for Node {
     fn as_ref(&self) -> &Node {
-        &self
+        self
+    }
+}
+
+// Where a number stream stopped making sense as a tree
+//
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    // The stream ended at `offset`, part way through the node whose header is at `node`
+    Truncated { node: usize, offset: usize },
+    // The tree was complete, but more numbers follow from `offset`
+    TrailingData { offset: usize },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Truncated { node, offset } => write!(
+                f,
+                "stream ended at offset {} inside the node starting at offset {}",
+                offset, node
+            ),
+            ParseError::TrailingData { offset } => {
+                write!(f, "unexpected data after the tree at offset {}", offset)
+            }
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+// A node still being parsed, and how many of its children remain to be read
+//
+struct Partial {
+    node: Node,
+    start: usize,
+    children: usize,
+    metadata: usize,
+}
+
+impl Drop for Node {
+    // Dropping the children recursively would overflow the stack for very deep trees
+    fn drop(&mut self) {
+        let mut stack = mem::take(&mut self.children);
+        while let Some(mut node) = stack.pop() {
+            stack.append(&mut node.children);
+        }
     }
 }
 
 impl Node {
-    fn construct(stream: &[usize]) -> Node {
-        let (num_children, num_metadata) = (stream[0], stream[1]);
-        let mut node = Node {
-            length: 2,
-            ..Node::default()
+    // Parse a tree from its number stream, keeping the nodes being read on an explicit stack
+    // (rather than the call stack) so that trees of any depth can be read
+    //
+    pub fn parse(stream: &[usize]) -> Result<Node, ParseError> {
+        let mut offset = 0;
+        let mut stack: Vec<Partial> = Vec::new();
+
+        let read = |offset: &mut usize, node: usize| match stream.get(*offset) {
+            Some(&n) => {
+                *offset += 1;
+                Ok(n)
+            }
+            None => Err(ParseError::Truncated {
+                node,
+                offset: *offset,
+            }),
         };
 
-        for _ in 0..num_children {
-            let child = Node::construct(&stream[node.length..]);
-            node.length += child.length;
-            node.children.push(child);
+        loop {
+            let pending = match stack.last_mut() {
+                Some(partial) if partial.children > 0 => {
+                    partial.children -= 1;
+                    true
+                }
+                Some(_) => false,
+                None => true, // The root
+            };
+
+            if pending {
+                let start = offset;
+                let children = read(&mut offset, start)?;
+                let metadata = read(&mut offset, start)?;
+                stack.push(Partial {
+                    node: Node::default(),
+                    start,
+                    children,
+                    metadata,
+                });
+                continue;
+            }
+
+            // Every child has been read, so the metadata follows
+            let mut partial = stack.pop().unwrap();
+            for _ in 0..partial.metadata {
+                let meta = read(&mut offset, partial.start)?;
+                partial.node.metadata.push(meta);
+            }
+            partial.node.length = offset - partial.start;
+            let node = mem::take(&mut partial.node);
+
+            match stack.last_mut() {
+                Some(parent) => parent.node.children.push(node),
+                None if offset < stream.len() => return Err(ParseError::TrailingData { offset }),
+                None => return Ok(node),
+            }
         }
+    }
+
+    pub fn children(&self) -> &[Node] {
+        &self.children
+    }
+
+    pub fn metadata(&self) -> &[usize] {
+        &self.metadata
+    }
 
-        for _ in 0..num_metadata {
-            let meta = stream[node.length];
-            node.metadata.push(meta);
-            node.length += 1;
+    // A depth-first walk over the tree, entering each node before and exiting it after its
+    // children
+    //
+    pub fn walk(&self) -> Walk<'_> {
+        Walk {
+            stack: vec![Visit::Enter(self)],
         }
+    }
+
+    pub fn pre_order(&self) -> impl Iterator<Item = &Node> {
+        self.walk().filter_map(|v| match v {
+            Visit::Enter(node) => Some(node),
+            Visit::Exit(_) => None,
+        })
+    }
 
-        node
+    pub fn post_order(&self) -> impl Iterator<Item = &Node> {
+        self.walk().filter_map(|v| match v {
+            Visit::Enter(_) => None,
+            Visit::Exit(node) => Some(node),
+        })
     }
 
-    fn sum_metadata(&self) -> usize {
-        let sum: usize = self.children.iter().map(|c| c.sum_metadata()).sum(); // Sum of children
-        sum + self.metadata.iter().sum::<usize>() // Sum of self
+    // Write the tree back out as the number stream it was parsed from
+    //
+    pub fn to_stream(&self) -> Vec<usize> {
+        let mut stream = Vec::with_capacity(self.length);
+
+        for visit in self.walk() {
+            match visit {
+                Visit::Enter(node) => {
+                    stream.push(node.children.len());
+                    stream.push(node.metadata.len());
+                }
+                Visit::Exit(node) => stream.extend(&node.metadata),
+            }
+        }
+
+        stream
+    }
+
+    pub fn stats(&self) -> TreeStats {
+        let mut stats = TreeStats::default();
+        let mut depth = 0;
+
+        for visit in self.walk() {
+            match visit {
+                Visit::Enter(node) => {
+                    depth += 1;
+                    stats.depth = stats.depth.max(depth);
+                    stats.nodes += 1;
+                    stats.metadata += node.metadata.len();
+                    if node.children.is_empty() {
+                        stats.leaves += 1;
+                    }
+                }
+                Visit::Exit(_) => depth -= 1,
+            }
+        }
+
+        stats
+    }
+
+    // The value of every node, in post-order (so the root's value is last)
+    //
+    fn values(&self) -> Vec<usize> {
+        let mut values = Vec::new();
+        let mut pending: Vec<usize> = Vec::new(); // Values of nodes whose parent is yet to be exited
+
+        for node in self.post_order() {
+            let children = pending.split_off(pending.len() - node.children.len());
+            let value = if children.is_empty() {
+                node.metadata.iter().sum()
+            } else {
+                // Only the metadata that index a child count (indexing starts at 1)
+                node.metadata
+                    .iter()
+                    .filter(|&&m| m != 0 && m <= children.len())
+                    .map(|&m| children[m - 1])
+                    .sum()
+            };
+            pending.push(value);
+            values.push(value);
+        }
+
+        values
     }
 
-    fn sum_complex(&self) -> usize {
-        if self.children.is_empty() {
-            return self.metadata.iter().sum::<usize>();
+    // Export the tree as a Graphviz digraph, labelling each node with its metadata and value
+    //
+    pub fn to_dot(&self) -> String {
+        let values = self.values();
+        let mut dot = String::from("digraph license {\n");
+        let mut pending: Vec<usize> = Vec::new(); // Ids of nodes whose parent is yet to be exited
+
+        // Nodes are numbered in post-order, to line up with their values
+        for (id, node) in self.post_order().enumerate() {
+            let children = pending.split_off(pending.len() - node.children.len());
+            let metadata: Vec<String> = node.metadata.iter().map(|m| m.to_string()).collect();
+
+            dot.push_str(&format!(
+                "    n{} [label=\"metadata: {}\\nvalue: {}\"];\n",
+                id,
+                metadata.join(" "),
+                values[id]
+            ));
+            for child in children {
+                dot.push_str(&format!("    n{} -> n{};\n", id, child));
+            }
+            pending.push(id);
         }
 
-        // Get all children that can be indexed by the metadata (accounting for indexing starting at 1)
-        let sum_iter = self
-            .metadata
-            .iter()
-            .filter(|&&m| m < self.children.len() + 1 && m != 0)
-            .map(|&m| &self.children[m - 1]);
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn sum_metadata(&self) -> usize {
+        self.pre_order()
+            .map(|node| node.metadata.iter().sum::<usize>())
+            .sum()
+    }
+
+    pub fn sum_complex(&self) -> usize {
+        *self.values().last().unwrap()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Visit<'a> {
+    Enter(&'a Node),
+    Exit(&'a Node),
+}
+
+pub struct Walk<'a> {
+    stack: Vec<Visit<'a>>,
+}
+
+impl<'a> Iterator for Walk<'a> {
+    type Item = Visit<'a>;
 
-        sum_iter.map(|c| c.sum_complex()).sum()
+    fn next(&mut self) -> Option<Visit<'a>> {
+        let visit = self.stack.pop()?;
+        if let Visit::Enter(node) = visit {
+            self.stack.push(Visit::Exit(node));
+            self.stack
+                .extend(node.children.iter().rev().map(Visit::Enter));
+        }
+        Some(visit)
     }
 }
 
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TreeStats {
+    pub nodes: usize,
+    pub leaves: usize,
+    pub depth: usize, // The number of nodes on the longest path from the root to a leaf
+    pub metadata: usize,
+}
+
 #[aoc_generator(day8)]
 pub fn input_tree(input: &str) -> Node {
     let stream: Vec<usize> = input
         .split_whitespace()
         .map(|n| n.parse().unwrap())
         .collect();
-    Node::construct(&stream)
+    Node::parse(&stream).unwrap()
 }
 
 #[aoc(day8, part1)]
@@ -91,4 +323,94 @@ mod tests {
     fn sample2() {
         assert_eq!(part2(&input_tree(TEST_STR)), 66);
     }
+
+    #[test]
+    fn deep_tree() {
+        // A chain of nodes each with one child and a single metadata entry of 1
+        let depth = 200_000;
+        let mut stream = vec![1; 2 * depth];
+        stream.extend(&[0, 1, 1]);
+        stream.extend(vec![1; depth]);
+
+        let root = Node::parse(&stream).unwrap();
+        assert_eq!(root.length, stream.len());
+        assert_eq!(part1(&root), depth + 1);
+        assert_eq!(part2(&root), 1);
+        assert_eq!(root.stats().depth, depth + 1);
+        assert_eq!(root.to_stream(), stream);
+    }
+
+    #[test]
+    fn round_trip() {
+        let stream: Vec<usize> = TEST_STR.split(' ').map(|n| n.parse().unwrap()).collect();
+        let root = Node::parse(&stream).unwrap();
+
+        assert_eq!(root.to_stream(), stream);
+        assert_eq!(Node::parse(&root.to_stream()), Ok(root));
+    }
+
+    #[test]
+    fn traversal() {
+        let root = input_tree(TEST_STR);
+        let pre: Vec<&[usize]> = root.pre_order().map(|n| n.metadata()).collect();
+        let post: Vec<&[usize]> = root.post_order().map(|n| n.metadata()).collect();
+
+        // A, B, C, D from the puzzle description
+        assert_eq!(pre, vec![&[1, 1, 2][..], &[10, 11, 12], &[2], &[99]]);
+        assert_eq!(post, vec![&[10, 11, 12][..], &[99], &[2], &[1, 1, 2]]);
+        assert_eq!(root.children()[1].children().len(), 1);
+        assert_eq!(
+            root.stats(),
+            TreeStats {
+                nodes: 4,
+                leaves: 2,
+                depth: 3,
+                metadata: 8
+            }
+        );
+    }
+
+    #[test]
+    fn dot() {
+        let expected = "digraph license {\n\
+                        \x20   n0 [label=\"metadata: 10 11 12\\nvalue: 33\"];\n\
+                        \x20   n1 [label=\"metadata: 99\\nvalue: 99\"];\n\
+                        \x20   n2 [label=\"metadata: 2\\nvalue: 0\"];\n\
+                        \x20   n2 -> n1;\n\
+                        \x20   n3 [label=\"metadata: 1 1 2\\nvalue: 66\"];\n\
+                        \x20   n3 -> n0;\n\
+                        \x20   n3 -> n2;\n\
+                        }\n";
+
+        assert_eq!(input_tree(TEST_STR).to_dot(), expected);
+    }
+
+    #[test]
+    fn malformed() {
+        let parse = |s: &str| {
+            let stream: Vec<usize> = s.split_whitespace().map(|n| n.parse().unwrap()).collect();
+            Node::parse(&stream)
+        };
+
+        assert_eq!(parse(""), Err(ParseError::Truncated { node: 0, offset: 0 }));
+        assert_eq!(
+            parse("2 3 0 3 10 11"),
+            Err(ParseError::Truncated { node: 2, offset: 6 })
+        );
+        assert_eq!(
+            parse("2 3 0 3 10 11 12 1 1 0 1 99 2 1 1"),
+            Err(ParseError::Truncated {
+                node: 0,
+                offset: 15
+            })
+        );
+        assert_eq!(
+            parse(&format!("{} 7", TEST_STR)),
+            Err(ParseError::TrailingData { offset: 16 })
+        );
+        assert_eq!(
+            ParseError::TrailingData { offset: 16 }.to_string(),
+            "unexpected data after the tree at offset 16"
+        );
+    }
 }
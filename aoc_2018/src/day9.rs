@@ -1,7 +1,15 @@
-//Day 7: The Sum of Its Parts
+//Day 9: Marble Mania
 //
 use regex::Regex;
-use std::collections::{HashMap, VecDeque};
+use std::collections::VecDeque;
+use std::convert::{Infallible, TryFrom};
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 pub struct GameInfo {
     players: usize,
@@ -10,7 +18,7 @@ pub struct GameInfo {
 
 impl std::convert::AsRef<GameInfo> for GameInfo {
     fn as_ref(&self) -> &GameInfo {
-        &self
+        self
     }
 }
 
@@ -33,20 +41,254 @@ pub fn input_gameinfo(input: &str) -> GameInfo {
 
 #[aoc(day9, part1)]
 pub fn part1(input: &GameInfo) -> usize {
-    let mut marbles = VecDeque::with_capacity(input.last_marble);
-    marbles.push_back(0);
-
-    let mut circle = Circle { marbles };
-    circle.play_game(input.players, input.last_marble)
+    let mut circle = Circle::with_capacity(input.last_marble);
+    circle
+        .play_game(input.players, input.last_marble, &Rules::default(), false)
+        .high_score()
 }
 
 #[aoc(day9, part2)]
 pub fn part2(input: &GameInfo) -> usize {
-    let mut marbles = VecDeque::with_capacity(input.last_marble * 100);
-    marbles.push_back(0);
+    let mut circle = Circle::with_capacity(input.last_marble * 100);
+    circle
+        .play_game(
+            input.players,
+            input.last_marble * 100,
+            &Rules::default(),
+            false,
+        )
+        .high_score()
+}
+
+// The same game on the other circles, to compare them with `cargo aoc bench`
+//
+#[aoc(day9, part2, naive)]
+pub fn part2_naive(input: &GameInfo) -> usize {
+    let mut circle = StepCircle::with_capacity(input.last_marble * 100);
+    circle
+        .play_game(
+            input.players,
+            input.last_marble * 100,
+            &Rules::default(),
+            false,
+        )
+        .high_score()
+}
+
+#[aoc(day9, part2, arena)]
+pub fn part2_arena(input: &GameInfo) -> usize {
+    let mut circle = LinkedCircle::with_capacity(input.last_marble * 100);
+    circle
+        .play_game(
+            input.players,
+            input.last_marble * 100,
+            &Rules::default(),
+            false,
+        )
+        .high_score()
+}
+
+#[aoc(day9, part2, spill)]
+pub fn part2_spill(input: &GameInfo) -> usize {
+    let mut circle = SpillCircle::new(std::env::temp_dir(), SPILL_BUDGET).unwrap();
+    circle
+        .try_play_game(
+            input.players,
+            input.last_marble * 100,
+            &Rules::default(),
+            false,
+        )
+        .unwrap()
+        .high_score()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rules {
+    pub divisor: usize, // Marbles that are a multiple of this are kept instead of placed
+    pub insertion_step: usize, // Marbles are placed this many marbles clockwise of the current one
+    pub removal_offset: usize, // Keeping a marble also removes the one this far counter clockwise
+}
+
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules {
+            divisor: 23,
+            insertion_step: 2,
+            removal_offset: 7,
+        }
+    }
+}
+
+impl Rules {
+    pub fn new(
+        divisor: usize,
+        insertion_step: usize,
+        removal_offset: usize,
+    ) -> Result<Rules, ZeroDivisor> {
+        if divisor == 0 {
+            return Err(ZeroDivisor);
+        }
+
+        Ok(Rules {
+            divisor,
+            insertion_step,
+            removal_offset,
+        })
+    }
+}
+
+// Rules under which no marble could ever be played, as every number is a multiple of 0
+//
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ZeroDivisor;
+
+impl fmt::Display for ZeroDivisor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the divisor must be at least 1")
+    }
+}
+
+impl Error for ZeroDivisor {}
+
+// The points a player (numbered from 1) scored when playing a marble
+//
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Turn {
+    pub player: usize,
+    pub marble: usize,
+    pub points: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameResult {
+    pub scores: Vec<usize>, // The final score of each player, starting with player 1
+    pub history: Option<Vec<Turn>>, // Every turn of the game, if it was recorded
+}
+
+impl GameResult {
+    pub fn high_score(&self) -> usize {
+        self.scores.iter().cloned().max().unwrap_or(0)
+    }
+
+    // The players (numbered from 1) who share the high score
+    //
+    pub fn winners(&self) -> Vec<usize> {
+        let high_score = self.high_score();
+        (1..=self.scores.len())
+            .filter(|p| self.scores[p - 1] == high_score)
+            .collect()
+    }
+}
+
+// What a player does with their marble: place it, or keep it and remove another
+//
+enum Move {
+    Place(usize),
+    Keep,
+}
+
+// The game shared by every circle, with `make_move` carrying out each move. Keeping a marble
+// returns the marble removed, or None if the circle is empty, which ends the game early (see
+// `MarbleCircle::play_game`). The first error from a move ends the game too, and is returned.
+//
+fn play<E, F>(
+    players: usize,
+    last_marble: usize,
+    rules: &Rules,
+    record_history: bool,
+    mut make_move: F,
+) -> Result<GameResult, E>
+where
+    F: FnMut(Move) -> Result<Option<usize>, E>,
+{
+    assert!(rules.divisor > 0, "{}", ZeroDivisor);
+
+    let mut scores = vec![0; players];
+    let mut history = if record_history {
+        Some(Vec::with_capacity(last_marble))
+    } else {
+        None
+    };
+
+    let players = 1..=players;
+    let marbles = 1..=last_marble;
+
+    for (player, marble) in players.cycle().zip(marbles) {
+        let mut points = 0;
+
+        if marble % rules.divisor == 0 {
+            // Player keeps the marble in play, and removes the marble counter clockwise,
+            // making the next marble the current
+            match make_move(Move::Keep)? {
+                Some(removed) => points = marble + removed,
+                None => break,
+            }
+            scores[player - 1] += points;
+        } else {
+            make_move(Move::Place(marble))?;
+        }
+
+        if let Some(history) = history.as_mut() {
+            history.push(Turn {
+                player,
+                marble,
+                points,
+            });
+        }
+    }
+
+    Ok(GameResult { scores, history })
+}
+
+// A circle of marbles, with one of them being the current marble
+//
+pub trait MarbleCircle {
+    fn clockwise(&mut self, turns: usize);
+    fn counter_clockwise(&mut self, turns: usize);
+
+    // Place a marble counter clockwise of the current one, making it the current
+    fn place(&mut self, marble: usize);
+
+    // Remove the current marble, making the marble clockwise of it the current
+    fn remove(&mut self) -> usize;
+
+    fn len(&self) -> usize;
 
-    let mut circle = Circle { marbles };
-    circle.play_game(input.players, input.last_marble * 100)
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Play until `last_marble` has been played. Once a marble is kept with no marbles left in
+    // the circle to remove (only possible with a divisor of 1), the game ends early, and the
+    // result only holds the turns played up to then.
+    //
+    fn play_game(
+        &mut self,
+        players: usize,
+        last_marble: usize,
+        rules: &Rules,
+        record_history: bool,
+    ) -> GameResult {
+        let result = play(players, last_marble, rules, record_history, |m| {
+            Ok::<_, Infallible>(match m {
+                Move::Place(marble) => {
+                    self.clockwise(rules.insertion_step);
+                    self.place(marble);
+                    None
+                }
+                Move::Keep if self.is_empty() => None,
+                Move::Keep => {
+                    self.counter_clockwise(rules.removal_offset);
+                    Some(self.remove())
+                }
+            })
+        });
+
+        match result {
+            Ok(result) => result,
+            Err(never) => match never {},
+        }
+    }
 }
 
 #[derive(Default, PartialEq)]
@@ -55,42 +297,420 @@ pub struct Circle {
 }
 
 impl Circle {
-    pub fn clockwise(&mut self, turns: usize) {
+    pub fn with_capacity(capacity: usize) -> Circle {
+        let mut marbles = VecDeque::with_capacity(capacity);
+        marbles.push_back(0);
+        Circle { marbles }
+    }
+}
+
+impl MarbleCircle for Circle {
+    fn clockwise(&mut self, turns: usize) {
+        let len = self.marbles.len();
+        self.marbles.rotate_left(turns % len);
+    }
+
+    fn counter_clockwise(&mut self, turns: usize) {
+        let len = self.marbles.len();
+        self.marbles.rotate_right(turns % len);
+    }
+
+    fn place(&mut self, marble: usize) {
+        self.marbles.push_front(marble);
+    }
+
+    fn remove(&mut self) -> usize {
+        self.marbles.pop_front().unwrap()
+    }
+
+    fn len(&self) -> usize {
+        self.marbles.len()
+    }
+}
+
+// The original circle, which moves round one marble at a time. Kept as the baseline for
+// `cargo aoc bench`.
+//
+#[derive(Default, PartialEq)]
+pub struct StepCircle {
+    marbles: VecDeque<usize>, // The list of marbles in the circle (front of queue being the first marble)
+}
+
+impl StepCircle {
+    pub fn with_capacity(capacity: usize) -> StepCircle {
+        let mut marbles = VecDeque::with_capacity(capacity);
+        marbles.push_back(0);
+        StepCircle { marbles }
+    }
+}
+
+impl MarbleCircle for StepCircle {
+    fn clockwise(&mut self, turns: usize) {
         for _ in 0..turns {
             let popped = self.marbles.pop_front().unwrap();
             self.marbles.push_back(popped);
         }
     }
 
-    pub fn counter_clockwise(&mut self, turns: usize) {
+    fn counter_clockwise(&mut self, turns: usize) {
         for _ in 0..turns {
             let popped = self.marbles.pop_back().unwrap();
             self.marbles.push_front(popped);
         }
     }
 
-    pub fn play_game(&mut self, players: usize, last_marble: usize) -> usize {
-        let mut scores: HashMap<usize, usize> = HashMap::new();
+    fn place(&mut self, marble: usize) {
+        self.marbles.push_front(marble);
+    }
+
+    fn remove(&mut self) -> usize {
+        self.marbles.pop_front().unwrap()
+    }
+
+    fn len(&self) -> usize {
+        self.marbles.len()
+    }
+}
+
+// Marbles are numbered from 0 upwards, so they can index their own links
+//
+fn marble_index(marble: usize) -> u32 {
+    u32::try_from(marble).expect("marble numbers must fit in 32 bits")
+}
+
+// A circle stored as a doubly linked list, with each marble's neighbours held in arrays
+// indexed by the marble itself. Every move is a single array lookup.
+//
+#[derive(Debug, PartialEq)]
+pub struct LinkedCircle {
+    next: Vec<u32>, // The marble clockwise of each marble
+    prev: Vec<u32>, // The marble counter clockwise of each marble
+    current: u32,
+    len: usize,
+}
+
+impl LinkedCircle {
+    pub fn with_capacity(last_marble: usize) -> LinkedCircle {
+        let mut next = Vec::with_capacity(last_marble + 1);
+        let mut prev = Vec::with_capacity(last_marble + 1);
+        next.push(0);
+        prev.push(0);
+
+        LinkedCircle {
+            next,
+            prev,
+            current: 0,
+            len: 1,
+        }
+    }
+
+    // The marbles in clockwise order, starting from the current one
+    //
+    pub fn marbles(&self) -> Vec<usize> {
+        let mut marbles = Vec::with_capacity(self.len);
+        let mut marble = self.current;
+        for _ in 0..self.len {
+            marbles.push(marble as usize);
+            marble = self.next[marble as usize];
+        }
+        marbles
+    }
+}
+
+impl MarbleCircle for LinkedCircle {
+    fn clockwise(&mut self, turns: usize) {
+        for _ in 0..turns {
+            self.current = self.next[self.current as usize];
+        }
+    }
 
-        let players = 1..=players;
-        let marbles = 1..=last_marble;
+    fn counter_clockwise(&mut self, turns: usize) {
+        for _ in 0..turns {
+            self.current = self.prev[self.current as usize];
+        }
+    }
+
+    fn place(&mut self, marble: usize) {
+        let index = marble_index(marble);
+        if marble >= self.next.len() {
+            self.next.resize(marble + 1, index);
+            self.prev.resize(marble + 1, index);
+        }
+
+        if self.is_empty() {
+            self.next[marble] = index;
+            self.prev[marble] = index;
+        } else {
+            let after = self.current;
+            let before = self.prev[after as usize];
+            self.next[marble] = after;
+            self.prev[marble] = before;
+            self.next[before as usize] = index;
+            self.prev[after as usize] = index;
+        }
+
+        self.current = index;
+        self.len += 1;
+    }
 
-        for (player, marble) in players.cycle().zip(marbles) {
-            if marble % 23 == 0 {
-                let score = scores.entry(player).or_insert(0);
-                *score += marble; // Player adds the marble in play to their score
+    fn remove(&mut self) -> usize {
+        assert!(!self.is_empty(), "no marbles left to remove");
 
-                // Remove the marble 7 positions counter clockwise, and make the next marble the current
-                self.counter_clockwise(7);
-                *score += self.marbles.pop_front().unwrap();
-                continue; //On to the next round!
+        let removed = self.current;
+        let before = self.prev[removed as usize];
+        let after = self.next[removed as usize];
+        self.next[before as usize] = after;
+        self.prev[after as usize] = before;
+
+        self.current = after;
+        self.len -= 1;
+        removed as usize
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+const SPILL_BUDGET: usize = 64 * 1024 * 1024;
+
+// Distinguishes the segment files of circles in the same process
+static SPILL_ID: AtomicUsize = AtomicUsize::new(0);
+
+// A run of marbles written out to its own file
+//
+struct Segment {
+    path: PathBuf,
+    len: usize,
+}
+
+// A circle that holds at most a fixed number of bytes of marbles in memory.
+//
+// Play only ever happens a few marbles either side of the current marble, so the circle is
+// kept as a queue starting at the current marble: a head and a tail in memory, with the
+// (long) run of marbles between them spilled to disk in segments. Moving round the circle
+// streams segments back in at one end and out at the other.
+//
+// Any move can fail on a disk error, so this circle doesn't implement `MarbleCircle`, and
+// is played with `try_play_game` instead.
+//
+pub struct SpillCircle {
+    head: VecDeque<u32>, // Marbles clockwise of the current one, starting with the current
+    middle: VecDeque<Segment>,
+    tail: VecDeque<u32>, // Marbles counter clockwise of the current one, ending with its neighbour
+    segment: usize,      // The number of marbles in a segment
+    dir: PathBuf,
+    id: usize,
+    written: usize, // Segments written so far, used to name the next one
+    failed: bool,   // Whether marbles were lost to a disk error
+}
+
+impl SpillCircle {
+    // A circle holding just marble 0, which spills to files in `dir` once the marbles it holds
+    // would take up more than `budget` bytes
+    //
+    pub fn new<P: AsRef<Path>>(dir: P, budget: usize) -> io::Result<SpillCircle> {
+        let dir = dir.as_ref().to_path_buf();
+        if !fs::metadata(&dir)?.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not a directory", dir.display()),
+            ));
+        }
+
+        // The head and tail each hold up to two segments, and are never reallocated. The
+        // rest of the budget is left for buffering the segment being written or read.
+        let segment = (budget / (5 * std::mem::size_of::<u32>())).max(1);
+        let mut head = VecDeque::with_capacity(2 * segment);
+        head.push_back(0);
+
+        Ok(SpillCircle {
+            head,
+            middle: VecDeque::new(),
+            tail: VecDeque::with_capacity(2 * segment),
+            segment,
+            dir,
+            id: SPILL_ID.fetch_add(1, Ordering::Relaxed),
+            written: 0,
+            failed: false,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.head.len() + self.middle.iter().map(|s| s.len).sum::<usize>() + self.tail.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // The number of files the circle is currently spilled over
+    //
+    pub fn segments(&self) -> usize {
+        self.middle.len()
+    }
+
+    // Play a game as `MarbleCircle::play_game` does, stopping at the first disk error. Marbles
+    // may be lost with it, so a circle that has failed can't be played again.
+    //
+    pub fn try_play_game(
+        &mut self,
+        players: usize,
+        last_marble: usize,
+        rules: &Rules,
+        record_history: bool,
+    ) -> io::Result<GameResult> {
+        if self.failed {
+            return Err(io::Error::other(
+                "marbles were lost to an earlier disk error",
+            ));
+        }
+
+        let result = play(players, last_marble, rules, record_history, |m| match m {
+            Move::Place(marble) => {
+                self.clockwise(rules.insertion_step)?;
+                self.push_head(marble_index(marble))?;
+                Ok(None)
+            }
+            Move::Keep if self.is_empty() => Ok(None),
+            Move::Keep => {
+                self.counter_clockwise(rules.removal_offset)?;
+                Ok(Some(self.pop_front()? as usize))
             }
+        });
 
-            self.clockwise(2);
-            self.marbles.push_front(marble);
+        self.failed = result.is_err();
+        result
+    }
+
+    fn clockwise(&mut self, turns: usize) -> io::Result<()> {
+        for _ in 0..turns {
+            let marble = self.pop_front()?;
+            self.push_tail(marble)?;
+        }
+        Ok(())
+    }
+
+    fn counter_clockwise(&mut self, turns: usize) -> io::Result<()> {
+        for _ in 0..turns {
+            let marble = self.pop_back()?;
+            self.push_head(marble)?;
+        }
+        Ok(())
+    }
+
+    fn next_path(&mut self) -> PathBuf {
+        let path = self.dir.join(format!(
+            "day9-{}-{}-{}.marbles",
+            process::id(),
+            self.id,
+            self.written
+        ));
+        self.written += 1;
+        path
+    }
+
+    fn write_segment<'a, I>(path: PathBuf, marbles: I) -> io::Result<Segment>
+    where
+        I: Iterator<Item = &'a u32>,
+    {
+        let mut writer = BufWriter::new(File::create(&path)?);
+        let mut len = 0;
+        for marble in marbles {
+            writer.write_all(&marble.to_le_bytes())?;
+            len += 1;
+        }
+        writer.flush()?;
+
+        Ok(Segment { path, len })
+    }
+
+    // Read a segment onto the end of `marbles`, removing its file
+    //
+    fn read_segment(segment: &Segment, marbles: &mut VecDeque<u32>) -> io::Result<()> {
+        let mut reader = BufReader::new(File::open(&segment.path)?);
+        let mut bytes = [0; 4];
+        for _ in 0..segment.len {
+            reader.read_exact(&mut bytes)?;
+            marbles.push_back(u32::from_le_bytes(bytes));
+        }
+        fs::remove_file(&segment.path)
+    }
+
+    // Push onto the front of the head, first spilling its inner end if it is full. Marbles
+    // are written straight from the head, and only dropped from it once they are on disk.
+    //
+    fn push_head(&mut self, marble: u32) -> io::Result<()> {
+        if self.head.len() >= 2 * self.segment {
+            let at = self.head.len() - self.segment;
+            let path = self.next_path();
+            let segment = Self::write_segment(path, self.head.range(at..))?;
+            self.head.truncate(at);
+            self.middle.push_front(segment);
         }
+        self.head.push_front(marble);
+        Ok(())
+    }
 
-        *scores.values().max().unwrap()
+    // Push onto the back of the tail, first spilling its inner end if it is full
+    //
+    fn push_tail(&mut self, marble: u32) -> io::Result<()> {
+        if self.tail.len() >= 2 * self.segment {
+            let path = self.next_path();
+            let segment = Self::write_segment(path, self.tail.range(..self.segment))?;
+            self.tail.drain(..self.segment);
+            self.middle.push_back(segment);
+        }
+        self.tail.push_back(marble);
+        Ok(())
+    }
+
+    // Take the current marble off the head
+    //
+    fn pop_front(&mut self) -> io::Result<u32> {
+        if self.head.is_empty() {
+            match self.middle.pop_front() {
+                Some(segment) => {
+                    // Keep the segment until it is read, so its file is removed on drop
+                    let read = Self::read_segment(&segment, &mut self.head);
+                    if read.is_err() {
+                        self.middle.push_front(segment);
+                    }
+                    read?;
+                }
+                None => std::mem::swap(&mut self.head, &mut self.tail),
+            }
+        }
+        Ok(self
+            .head
+            .pop_front()
+            .expect("no marbles left in the circle"))
+    }
+
+    // Take the marble counter clockwise of the current one off the tail
+    //
+    fn pop_back(&mut self) -> io::Result<u32> {
+        if self.tail.is_empty() {
+            match self.middle.pop_back() {
+                Some(segment) => {
+                    let read = Self::read_segment(&segment, &mut self.tail);
+                    if read.is_err() {
+                        self.middle.push_back(segment);
+                    }
+                    read?;
+                }
+                None => std::mem::swap(&mut self.head, &mut self.tail),
+            }
+        }
+        Ok(self.tail.pop_back().expect("no marbles left in the circle"))
+    }
+}
+
+impl Drop for SpillCircle {
+    fn drop(&mut self) {
+        for segment in &self.middle {
+            let _ = fs::remove_file(&segment.path);
+        }
     }
 }
 
@@ -104,4 +724,234 @@ mod tests {
     fn sample1() {
         assert_eq!(part1(&input_gameinfo(TEST_STR)), 32);
     }
+
+    #[test]
+    fn samples() {
+        let high_score = |players, last_marble| {
+            Circle::with_capacity(last_marble)
+                .play_game(players, last_marble, &Rules::default(), false)
+                .high_score()
+        };
+
+        assert_eq!(high_score(10, 1618), 8317);
+        assert_eq!(high_score(13, 7999), 146373);
+        assert_eq!(high_score(17, 1104), 2764);
+        assert_eq!(high_score(21, 6111), 54718);
+        assert_eq!(high_score(30, 5807), 37305);
+    }
+
+    #[test]
+    fn scoreboard() {
+        let result = Circle::with_capacity(25).play_game(9, 25, &Rules::default(), true);
+        let history = result.history.as_ref().unwrap();
+
+        assert_eq!(result.scores, vec![0, 0, 0, 0, 32, 0, 0, 0, 0]);
+        assert_eq!(result.winners(), vec![5]);
+        assert_eq!(history.len(), 25);
+        assert_eq!(
+            history[22],
+            Turn {
+                player: 5,
+                marble: 23,
+                points: 32
+            }
+        );
+        assert_eq!(history.iter().map(|t| t.points).sum::<usize>(), 32);
+    }
+
+    #[test]
+    fn custom_rules() {
+        let rules = Rules {
+            divisor: 5,
+            insertion_step: 1,
+            removal_offset: 2,
+        };
+        let mut circle = Circle::with_capacity(10);
+        let result = circle.play_game(2, 10, &rules, false);
+
+        // Each marble is placed directly after the current one, so player 1 keeps 5 and
+        // removes 2 from 0 1 2 3 4, and player 2 keeps 10 and removes 7
+        assert_eq!(result.scores, vec![7, 17]);
+        assert_eq!(result.winners(), vec![2]);
+        assert_eq!(circle.marbles, vec![8, 9, 4, 0, 1, 3, 6]);
+    }
+
+    #[test]
+    fn checked_rules() {
+        assert_eq!(Rules::new(23, 2, 7), Ok(Rules::default()));
+        assert_eq!(Rules::new(0, 2, 7), Err(ZeroDivisor));
+    }
+
+    #[test]
+    fn out_of_marbles() {
+        // Marble 1 is kept and removes marble 0, leaving nothing for marble 2 to remove
+        let rules = Rules::new(1, 2, 7).unwrap();
+        let expected = GameResult {
+            scores: vec![1, 0],
+            history: Some(vec![Turn {
+                player: 1,
+                marble: 1,
+                points: 1,
+            }]),
+        };
+
+        let mut circle = Circle::with_capacity(10);
+        assert_eq!(circle.play_game(2, 10, &rules, true), expected);
+        assert!(circle.is_empty());
+
+        let mut stepped = StepCircle::with_capacity(10);
+        assert_eq!(stepped.play_game(2, 10, &rules, true), expected);
+        assert!(stepped.is_empty());
+
+        let mut linked = LinkedCircle::with_capacity(10);
+        assert_eq!(linked.play_game(2, 10, &rules, true), expected);
+        assert!(linked.is_empty());
+
+        let mut spilled = SpillCircle::new(std::env::temp_dir(), 64).unwrap();
+        assert_eq!(
+            spilled.try_play_game(2, 10, &rules, true).unwrap(),
+            expected
+        );
+        assert!(spilled.is_empty());
+    }
+
+    #[test]
+    fn circles_agree() {
+        let dir = std::env::temp_dir();
+        let games = [
+            (9, 25, Rules::default()),
+            (10, 1618, Rules::default()),
+            (
+                4,
+                500,
+                Rules {
+                    divisor: 7,
+                    insertion_step: 3,
+                    removal_offset: 11,
+                },
+            ),
+            (
+                3,
+                300,
+                Rules {
+                    divisor: 4,
+                    insertion_step: 0,
+                    removal_offset: 1,
+                },
+            ),
+        ];
+
+        for &(players, last_marble, rules) in &games {
+            let expected =
+                Circle::with_capacity(last_marble).play_game(players, last_marble, &rules, true);
+
+            let mut stepped = StepCircle::with_capacity(last_marble);
+            assert_eq!(
+                stepped.play_game(players, last_marble, &rules, true),
+                expected
+            );
+
+            let mut linked = LinkedCircle::with_capacity(last_marble);
+            assert_eq!(
+                linked.play_game(players, last_marble, &rules, true),
+                expected
+            );
+            assert_eq!(stepped.len(), linked.len());
+
+            // A tiny budget, so that marbles are spilled every few moves
+            for &budget in &[64, 1024, SPILL_BUDGET] {
+                let mut spilled = SpillCircle::new(&dir, budget).unwrap();
+                assert_eq!(
+                    spilled
+                        .try_play_game(players, last_marble, &rules, true)
+                        .unwrap(),
+                    expected
+                );
+                assert_eq!(spilled.len(), linked.len());
+            }
+        }
+    }
+
+    #[test]
+    fn linked_circle() {
+        let mut circle = LinkedCircle::with_capacity(0);
+        circle.play_game(9, 25, &Rules::default(), false);
+
+        // The circle from the puzzle description, after marble 25 is placed
+        assert_eq!(
+            circle.marbles(),
+            vec![
+                25, 10, 21, 5, 22, 11, 1, 12, 6, 13, 3, 14, 7, 15, 0, 16, 8, 17, 4, 18, 19, 2, 24,
+                20
+            ]
+        );
+    }
+
+    #[test]
+    fn spilled_files_removed() {
+        let dir = std::env::temp_dir().join(format!("day9-spill-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut circle = SpillCircle::new(&dir, 64).unwrap();
+        circle
+            .try_play_game(10, 1618, &Rules::default(), false)
+            .unwrap();
+        assert!(circle.segments() > 0);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), circle.segments());
+
+        drop(circle);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        fs::remove_dir(&dir).unwrap();
+
+        assert!(SpillCircle::new(&dir, 64).is_err());
+    }
+
+    #[test]
+    fn spill_budget() {
+        let rules = Rules::new(7, 3, 11).unwrap();
+
+        for &budget in &[20, 64, 1024, 4096] {
+            let mut circle = SpillCircle::new(std::env::temp_dir(), budget).unwrap();
+            let result = circle.try_play_game(10, 5000, &rules, false).unwrap();
+
+            assert_eq!(
+                result,
+                Circle::with_capacity(5000).play_game(10, 5000, &rules, false)
+            );
+            assert!(circle.segments() > 0);
+            assert!(
+                (circle.head.capacity() + circle.tail.capacity()) * std::mem::size_of::<u32>()
+                    <= budget
+            );
+        }
+    }
+
+    #[test]
+    fn spill_failure() {
+        let dir = std::env::temp_dir().join(format!("day9-fail-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut circle = SpillCircle::new(&dir, 64).unwrap();
+        fs::remove_dir(&dir).unwrap();
+
+        // Nowhere to spill to, so the game can't be finished
+        let error = circle
+            .try_play_game(10, 1618, &Rules::default(), false)
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+
+        // Marbles were lost with it, so the circle can't be played again
+        fs::create_dir_all(&dir).unwrap();
+        let error = circle
+            .try_play_game(10, 1618, &Rules::default(), false)
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::Other);
+        fs::remove_dir(&dir).unwrap();
+    }
+
+    #[test]
+    fn tied() {
+        // Nobody scores before the first multiple of the divisor
+        let result = Circle::with_capacity(5).play_game(3, 5, &Rules::default(), false);
+        assert_eq!(result.winners(), vec![1, 2, 3]);
+    }
 }
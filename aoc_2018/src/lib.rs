@@ -18,12 +18,13 @@ pub mod day6;
 pub mod day7;
 pub mod day8;
 pub mod day9;
-// pub mod day10; -> This is compiled as a seperate binary `$ cargo run day10`
+pub mod day10;
 pub mod day11;
 pub mod day12;
 pub mod day13;
 pub mod day14;
 pub mod day15;
 pub mod day16;
+pub mod ocr;
 
 aoc_lib! { year = 2018 }
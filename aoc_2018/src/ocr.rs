@@ -0,0 +1,323 @@
+// Recognition of the block letters that some puzzles draw as their answer
+//
+use std::error::Error;
+use std::fmt;
+
+// The letters known to appear in answers, as they are drawn in each size of font
+//
+const SMALL: &[(char, &[&str])] = &[
+    ('A', &[".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', &["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', &[".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', &["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', &["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', &[".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', &["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', &["###", ".#.", ".#.", ".#.", ".#.", "###"]),
+    ('J', &["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', &["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', &["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', &[".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', &["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', &["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', &[".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', &["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Y', &["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+    ('Z', &["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+const LARGE: &[(char, &[&str])] = &[
+    (
+        'A',
+        &[
+            "..##..", ".#..#.", "#....#", "#....#", "#....#", "######", "#....#", "#....#",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'B',
+        &[
+            "#####.", "#....#", "#....#", "#....#", "#####.", "#....#", "#....#", "#....#",
+            "#....#", "#####.",
+        ],
+    ),
+    (
+        'C',
+        &[
+            ".####.", "#....#", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....",
+            "#....#", ".####.",
+        ],
+    ),
+    (
+        'E',
+        &[
+            "######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....",
+            "#.....", "######",
+        ],
+    ),
+    (
+        'F',
+        &[
+            "######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....",
+            "#.....", "#.....",
+        ],
+    ),
+    (
+        'G',
+        &[
+            ".####.", "#....#", "#.....", "#.....", "#.....", "#..###", "#....#", "#....#",
+            "#...##", ".###.#",
+        ],
+    ),
+    (
+        'H',
+        &[
+            "#....#", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'J',
+        &[
+            "...###", "....#.", "....#.", "....#.", "....#.", "....#.", "....#.", "#...#.",
+            "#...#.", ".###..",
+        ],
+    ),
+    (
+        'K',
+        &[
+            "#....#", "#...#.", "#..#..", "#.#...", "##....", "##....", "#.#...", "#..#..",
+            "#...#.", "#....#",
+        ],
+    ),
+    (
+        'L',
+        &[
+            "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....",
+            "#.....", "######",
+        ],
+    ),
+    (
+        'N',
+        &[
+            "#....#", "##...#", "##...#", "#.#..#", "#.#..#", "#..#.#", "#..#.#", "#...##",
+            "#...##", "#....#",
+        ],
+    ),
+    (
+        'P',
+        &[
+            "#####.", "#....#", "#....#", "#....#", "#####.", "#.....", "#.....", "#.....",
+            "#.....", "#.....",
+        ],
+    ),
+    (
+        'R',
+        &[
+            "#####.", "#....#", "#....#", "#....#", "#####.", "#..#..", "#...#.", "#...#.",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'X',
+        &[
+            "#....#", "#....#", ".#..#.", ".#..#.", "..##..", "..##..", ".#..#.", ".#..#.",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'Z',
+        &[
+            "######", ".....#", ".....#", "....#.", "...#..", "..#...", ".#....", "#.....",
+            "#.....", "######",
+        ],
+    ),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OcrError {
+    // Nothing is lit, so there is nothing to read
+    Blank,
+    // The letters are neither 6 nor 10 rows tall
+    UnsupportedHeight(usize),
+    // Some glyphs are not letters of the font. `text` is what could be read, with a `?` for
+    // each glyph not recognised, and `columns` the column each of those glyphs starts at.
+    Unrecognised { text: String, columns: Vec<usize> },
+}
+
+impl fmt::Display for OcrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OcrError::Blank => write!(f, "no lit cells to read"),
+            OcrError::UnsupportedHeight(h) => {
+                write!(f, "letters are {} rows tall, expected 6 or 10", h)
+            }
+            OcrError::Unrecognised { text, columns } => {
+                let columns: Vec<String> = columns.iter().map(|c| c.to_string()).collect();
+                write!(
+                    f,
+                    "unrecognised glyphs at columns {} (read \"{}\")",
+                    columns.join(", "),
+                    text
+                )
+            }
+        }
+    }
+}
+
+impl Error for OcrError {}
+
+// Read the letters drawn on a grid of lit (`true`) cells. Rows need not be the same length;
+// missing cells are unlit.
+//
+pub fn read(grid: &[Vec<bool>]) -> Result<String, OcrError> {
+    let lit = |r: usize, c: usize| grid[r].get(c).cloned().unwrap_or(false);
+    let width = grid.iter().map(|row| row.len()).max().unwrap_or(0);
+
+    let rows: Vec<usize> = (0..grid.len())
+        .filter(|&r| grid[r].iter().any(|&l| l))
+        .collect();
+    let (top, bottom) = match (rows.first(), rows.last()) {
+        (Some(&top), Some(&bottom)) => (top, bottom),
+        _ => return Err(OcrError::Blank),
+    };
+
+    let font = match bottom - top + 1 {
+        6 => SMALL,
+        10 => LARGE,
+        h => return Err(OcrError::UnsupportedHeight(h)),
+    };
+
+    // Letters are separated by at least one unlit column
+    let filled: Vec<bool> = (0..width)
+        .map(|c| (top..=bottom).any(|r| lit(r, c)))
+        .collect();
+    let mut glyphs = Vec::new();
+    let mut c = 0;
+    while c < width {
+        if filled[c] {
+            let start = c;
+            while c < width && filled[c] {
+                c += 1;
+            }
+            glyphs.push(start..c);
+        } else {
+            c += 1;
+        }
+    }
+
+    let mut text = String::new();
+    let mut columns = Vec::new();
+    for glyph in glyphs {
+        let drawn: Vec<String> = (top..=bottom)
+            .map(|r| {
+                glyph
+                    .clone()
+                    .map(|c| if lit(r, c) { '#' } else { '.' })
+                    .collect()
+            })
+            .collect();
+
+        match font.iter().find(|(_, rows)| drawn.iter().eq(rows.iter())) {
+            Some(&(letter, _)) => text.push(letter),
+            None => {
+                text.push('?');
+                columns.push(glyph.start);
+            }
+        }
+    }
+
+    if columns.is_empty() {
+        Ok(text)
+    } else {
+        Err(OcrError::Unrecognised { text, columns })
+    }
+}
+
+// Read the letters drawn in text, with `#` marking lit cells
+//
+pub fn read_str(drawing: &str) -> Result<String, OcrError> {
+    let grid: Vec<Vec<bool>> = drawing
+        .lines()
+        .map(|line| line.chars().map(|c| c == '#').collect())
+        .collect();
+    read(&grid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small() {
+        let drawing = "\
+            .##..###..#..#.####.###.\n\
+            #..#.#..#.#..#.#....#..#\n\
+            #..#.#..#.####.###..###.\n\
+            ####.###..#..#.#....#..#\n\
+            #..#.#....#..#.#....#..#\n\
+            #..#.#....#..#.####.###.\n";
+
+        assert_eq!(read_str(drawing), Ok("APHEB".to_string()));
+    }
+
+    #[test]
+    fn large() {
+        // Padded with unlit rows and columns, as a frame of lights would be
+        let drawing = "\
+            ...................\n\
+            .#....#..######....\n\
+            .##...#..#.........\n\
+            .##...#..#.........\n\
+            .#.#..#..#.........\n\
+            .#.#..#..#####.....\n\
+            .#..#.#..#.........\n\
+            .#..#.#..#.........\n\
+            .#...##..#.........\n\
+            .#...##..#.........\n\
+            .#....#..######....\n";
+
+        assert_eq!(read_str(drawing), Ok("NE".to_string()));
+    }
+
+    #[test]
+    fn fonts() {
+        for font in &[SMALL, LARGE] {
+            for (letter, rows) in font.iter() {
+                let grid: Vec<Vec<bool>> = rows
+                    .iter()
+                    .map(|row| row.chars().map(|c| c == '#').collect())
+                    .collect();
+                assert_eq!(read(&grid), Ok(letter.to_string()));
+            }
+        }
+    }
+
+    #[test]
+    fn unreadable() {
+        assert_eq!(read_str(""), Err(OcrError::Blank));
+        assert_eq!(read_str("...\n...\n"), Err(OcrError::Blank));
+        assert_eq!(read_str("#\n#\n#\n"), Err(OcrError::UnsupportedHeight(3)));
+
+        let drawing = "\
+            #..#..#..####\n\
+            #..#..#..#...\n\
+            ####..#..###.\n\
+            #..#..#..#...\n\
+            #..#..#..#...\n\
+            #..#..#..####\n";
+        let error = read_str(drawing).unwrap_err();
+
+        assert_eq!(
+            error,
+            OcrError::Unrecognised {
+                text: "H?E".to_string(),
+                columns: vec![6]
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "unrecognised glyphs at columns 6 (read \"H?E\")"
+        );
+    }
+}